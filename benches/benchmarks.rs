@@ -1,4 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use fasteval::{Compiler, Evaler};
 
 macro_rules! float_eq {
     ($op1:expr, $op2:expr) => {
@@ -82,5 +83,93 @@ fn bench_cmp(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_cmp, bench_mexe);
+// Expressions with variables, which compiling cannot fold to a constant.
+const VAR_EXPRESSIONS: [&str; 4] = [
+    "x * (y + 1.5) / (x - 2)",
+    "(x - 1) * (x + 1) - y / 3",
+    "((x + y) * 2.5 - (z / 4 + 6) * 3) / (1.5 + x * 0.1)",
+    "x * 2 + y * 3 + z * 4 - (x - y) * (y - z) * 0.5",
+];
+
+// The value of a variable in the `i`-th evaluation of `VAR_EXPRESSIONS`.
+fn value(name: &str, i: f64) -> f64 {
+    match name {
+        "x" => 3.5 + i,
+        "y" => 0.25 * i,
+        _ => 7.0 - i,
+    }
+}
+
+#[allow(unused_must_use)]
+fn bench_compiled(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bench_compiled");
+
+    for expr in VAR_EXPRESSIONS.iter() {
+        let compiled = mexe::compile(expr).unwrap();
+        let mut values: Vec<f64> = compiled
+            .vars()
+            .iter()
+            .map(|name| value(name, 0.0))
+            .collect();
+
+        let mut context = mexe::Context::new();
+        for name in ["x", "y", "z"] {
+            context.set(name, value(name, 0.0));
+        }
+
+        let mut slab = fasteval::Slab::new();
+        let fasteval_compiled = fasteval::Parser::new()
+            .parse(expr, &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .compile(&slab.ps, &mut slab.cs);
+
+        let mut namespace = |name: &str, _: Vec<f64>| Some(value(name, 0.0));
+        let expected = fasteval_compiled.eval(&slab, &mut namespace).unwrap();
+        float_eq!(compiled.eval(&values).unwrap(), expected);
+        float_eq!(
+            f64::try_from(context.eval(expr).unwrap()).unwrap(),
+            expected
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("bench_compiled mexe vm", expr),
+            expr,
+            |b, _| {
+                let mut i = 0.0;
+                b.iter(|| {
+                    i = (i + 1.0) % 100.0;
+                    for (slot, name) in values.iter_mut().zip(compiled.vars()) {
+                        *slot = value(name, black_box(i));
+                    }
+                    compiled.eval(&values)
+                });
+            },
+        );
+        // `Context::eval` parses the expression every time, which is what
+        // compiling once saves
+        group.bench_with_input(
+            BenchmarkId::new("bench_compiled mexe eval", expr),
+            expr,
+            |b, &expr| {
+                b.iter(|| context.eval(black_box(expr)));
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("bench_compiled fasteval", expr),
+            expr,
+            |b, _| {
+                let mut i = 0.0;
+                b.iter(|| {
+                    i = (i + 1.0) % 100.0;
+                    let mut namespace = |name: &str, _: Vec<f64>| Some(value(name, black_box(i)));
+                    fasteval_compiled.eval(&slab, &mut namespace)
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_cmp, bench_mexe, bench_compiled);
 criterion_main!(benches);
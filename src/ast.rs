@@ -0,0 +1,181 @@
+use crate::Operator;
+
+/// Abstract syntax tree of an arithmetic expression.
+///
+/// Obtained with [`parse`](crate::parse). Its [`Display`](std::fmt::Display)
+/// implementation prints it back as an expression that `mexe` can parse, with
+/// the minimum number of parentheses. See [`Ast::display`] for other spacing
+/// styles.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Ast {
+    /// A numeric literal
+    Number(f64),
+    /// A variable, such as `x`
+    Var(String),
+    /// Unary minus
+    Neg(Box<Ast>),
+    /// Logical negation (`!`)
+    Not(Box<Ast>),
+    /// Bitwise negation (`~`)
+    BitNot(Box<Ast>),
+    /// A binary operation between the left and right operands
+    Binary(Operator, Box<Ast>, Box<Ast>),
+    /// A conditional, `c ? a : b` or `if(c, a, b)`: the condition, the value
+    /// if it is true and the value if it is false
+    Cond(Box<Ast>, Box<Ast>, Box<Ast>),
+}
+
+impl Ast {
+    /// Returns a value that prints the expression with the given [`Spacing`].
+    ///
+    /// Only the parentheses required by precedence and associativity are
+    /// emitted, and the output can always be parsed back into the same tree.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), mexe::MexeError> {
+    /// use mexe::Spacing;
+    ///
+    /// let ast = mexe::parse("((2 * x)) + (y / (3 - z))")?;
+    /// assert_eq!(ast.display(Spacing::Spaced).to_string(), "2 * x + y / (3 - z)");
+    /// assert_eq!(ast.display(Spacing::Grouped).to_string(), "2*x + y/(3 - z)");
+    /// assert_eq!(ast.display(Spacing::Compact).to_string(), "2*x+y/(3-z)");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn display(&self, spacing: Spacing) -> AstDisplay<'_> {
+        AstDisplay { ast: self, spacing }
+    }
+
+    pub(crate) fn binary(op: Operator, lhs: Ast, rhs: Ast) -> Ast {
+        Ast::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    pub(crate) fn neg(ast: Ast) -> Ast {
+        Ast::Neg(Box::new(ast))
+    }
+
+    pub(crate) fn cond(cond: Ast, then: Ast, otherwise: Ast) -> Ast {
+        Ast::Cond(Box::new(cond), Box::new(then), Box::new(otherwise))
+    }
+
+    // Whether the node is an operation that binds at most as tightly as `+`.
+    pub(crate) fn is_sum(&self) -> bool {
+        matches!(self, Ast::Binary(op, _, _) if op.precedence() <= Operator::Add.precedence())
+    }
+
+    // Whether the node is read starting with a minus sign.
+    pub(crate) fn is_negative(&self) -> bool {
+        match self {
+            Ast::Number(n) => n.is_sign_negative(),
+            Ast::Neg(_) => true,
+            Ast::Binary(Operator::Mul, lhs, _) => lhs.is_negative(),
+            _ => false,
+        }
+    }
+
+    // Binding strength of the node, used to decide where parentheses go.
+    pub(crate) fn precedence(&self) -> u8 {
+        match self {
+            Ast::Cond(..) => 0,
+            Ast::Binary(op, _, _) => op.precedence(),
+            Ast::Number(_) | Ast::Var(_) | Ast::Neg(_) | Ast::Not(_) | Ast::BitNot(_) => 11,
+        }
+    }
+}
+
+/// Spacing style used when printing an [`Ast`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Spacing {
+    /// Spaces around all binary operators: `2 * x + 1`
+    #[default]
+    Spaced,
+    /// Spaces only around `+`, `-` and the operators that bind more loosely,
+    /// such as `<` and `&&`: `2*x + 1`
+    Grouped,
+    /// No spaces: `2*x+1`
+    Compact,
+}
+
+/// Prints an [`Ast`] with a given [`Spacing`], created by [`Ast::display`]
+#[derive(Clone, Copy, Debug)]
+pub struct AstDisplay<'a> {
+    ast: &'a Ast,
+    spacing: Spacing,
+}
+
+impl AstDisplay<'_> {
+    fn with<'b>(&self, ast: &'b Ast) -> AstDisplay<'b> {
+        ast.display(self.spacing)
+    }
+}
+
+impl std::fmt::Display for AstDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self.ast {
+            Ast::Number(n) => write!(f, "{}", n),
+            Ast::Var(name) => write!(f, "{}", name),
+            // the grammar only allows a minus before a number, a variable, a
+            // `!`, a `~` or a parenthesized expression, and a minus before a
+            // number is read as a negative number, so a negated number keeps
+            // its parentheses
+            Ast::Neg(ast) => match ast.as_ref() {
+                Ast::Var(name) => write!(f, "-{}", name),
+                ast @ (Ast::Not(_) | Ast::BitNot(_)) => write!(f, "-{}", self.with(ast)),
+                ast => write!(f, "-({})", self.with(ast)),
+            },
+            Ast::Not(ast) => match ast.as_ref() {
+                ast @ (Ast::Binary(..) | Ast::Cond(..)) => write!(f, "!({})", self.with(ast)),
+                ast => write!(f, "!{}", self.with(ast)),
+            },
+            Ast::BitNot(ast) => match ast.as_ref() {
+                ast @ (Ast::Binary(..) | Ast::Cond(..)) => write!(f, "~({})", self.with(ast)),
+                ast => write!(f, "~{}", self.with(ast)),
+            },
+            // conditionals are right-associative: only a conditional used as
+            // a condition needs parentheses
+            Ast::Cond(cond, then, otherwise) => {
+                if cond.precedence() == 0 {
+                    write!(f, "({})", self.with(cond))?;
+                } else {
+                    write!(f, "{}", self.with(cond))?;
+                }
+
+                match self.spacing {
+                    Spacing::Compact => write!(f, "?{}:{}", self.with(then), self.with(otherwise)),
+                    _ => write!(f, " ? {} : {}", self.with(then), self.with(otherwise)),
+                }
+            }
+            Ast::Binary(op, lhs, rhs) => {
+                let prec = self.ast.precedence();
+
+                if lhs.precedence() < prec {
+                    write!(f, "({})", self.with(lhs))?;
+                } else {
+                    write!(f, "{}", self.with(lhs))?;
+                }
+
+                // `xor` is a word, so it always needs spaces around it
+                match self.spacing {
+                    Spacing::Spaced => write!(f, " {} ", op)?,
+                    _ if *op == Operator::BitXor => write!(f, " {} ", op)?,
+                    Spacing::Grouped if self.ast.is_sum() => write!(f, " {} ", op)?,
+                    _ => write!(f, "{}", op)?,
+                }
+
+                // operators are left-associative
+                if rhs.precedence() <= prec {
+                    write!(f, "({})", self.with(rhs))
+                } else {
+                    write!(f, "{}", self.with(rhs))
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Ast {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "{}", self.display(Spacing::default()))
+    }
+}
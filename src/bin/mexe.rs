@@ -0,0 +1,249 @@
+//! Command-line calculator: evaluates the program given as arguments, or each
+//! line of the standard input, and prints the results. Also has an interactive
+//! mode.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+
+use mexe::{Context, MexeError, Value};
+
+const USAGE: &str = "\
+Usage: mexe [OPTIONS] [--] [PROGRAM]...
+
+Evaluates PROGRAM (all arguments joined with spaces) or, if none is given,
+each non-empty line of the standard input, and prints the results. A program
+is an expression, or statements separated by `;` such as `r = 2; pi * r * r`.
+Expressions can also compare values, as in `r > 1 && r != 3`, which results in
+`true` or `false`, and combine integers with bitwise operators, as in
+`(1 << 4) | 3`.
+Variables assigned in a line can be used in the following lines. Exits with
+status 1 if any program could not be evaluated.
+
+If no program is given and the standard input is a terminal, starts the
+interactive mode, where each result is also stored in `ans` and in `_1`,
+`_2`...
+
+Options:
+    --strict         treat division by zero as an error
+    --precision N    print results with N digits after the decimal point
+    --json           print a JSON object for each program (except in the
+                     interactive mode)
+    -i, --interactive
+                     start the interactive mode
+    -h, --help       print this help
+";
+
+struct Options {
+    strict: bool,
+    precision: Option<usize>,
+    json: bool,
+    interactive: bool,
+}
+
+fn main() -> ExitCode {
+    let mut options = Options {
+        strict: false,
+        precision: None,
+        json: false,
+        interactive: false,
+    };
+    let mut words = Vec::new();
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strict" => options.strict = true,
+            "--json" => options.json = true,
+            "-i" | "--interactive" => options.interactive = true,
+            "--precision" => match args.next().map(|n| n.parse()) {
+                Some(Ok(n)) => options.precision = Some(n),
+                _ => return usage_error("`--precision` expects a non-negative integer"),
+            },
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            "--" => {
+                words.extend(args);
+                break;
+            }
+            _ if arg.starts_with("--") => {
+                return usage_error(&format!("unknown option `{}`", arg));
+            }
+            _ => words.push(arg),
+        }
+    }
+
+    if options.interactive || words.is_empty() && io::stdin().is_terminal() {
+        return repl(&options);
+    }
+
+    let mut context = Context::new();
+
+    let ok = if words.is_empty() {
+        let mut ok = true;
+
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            if !line.trim().is_empty() {
+                ok &= run(&mut context, line.trim_end_matches('\r'), &options);
+            }
+        }
+
+        ok
+    } else {
+        run(&mut context, &words.join(" "), &options)
+    };
+
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+// Evaluates a program and prints the result, returning whether it succeeded.
+fn run(context: &mut Context, program: &str, options: &Options) -> bool {
+    let result = eval(context, program, options);
+
+    let ok = result.is_ok();
+
+    match (result, options.json) {
+        (Ok(val), false) => println!("{}", value(val, options.precision)),
+        (Ok(val), true) => {
+            let val = match val {
+                Value::Number(n) if !n.is_finite() => "null".to_owned(),
+                val => value(val, options.precision),
+            };
+
+            println!(
+                "{{\"expression\":{},\"result\":{}}}",
+                json_string(program),
+                val
+            );
+        }
+        (Err(err), false) => eprintln!("{}", diagnostic(program, &err)),
+        (Err(err), true) => {
+            let index = match err.index() {
+                Some(index) => index.to_string(),
+                None => "null".to_owned(),
+            };
+
+            println!(
+                "{{\"expression\":{},\"error\":{},\"index\":{}}}",
+                json_string(program),
+                json_string(&err.to_string()),
+                index
+            );
+        }
+    }
+
+    ok
+}
+
+// Reads programs until the end of the input. The result of each one is stored
+// in `ans` and in `_1`, `_2`...
+fn repl(options: &Options) -> ExitCode {
+    let mut context = Context::new();
+    let mut results = 0;
+    let mut line = String::new();
+
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+
+        line.clear();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) => {
+                println!();
+                return ExitCode::SUCCESS;
+            }
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match eval(&mut context, line, options) {
+            Ok(val) => {
+                results += 1;
+                let name = format!("_{}", results);
+
+                println!("{} = {}", name, value(val, options.precision));
+                context.set(name, val);
+                context.set("ans", val);
+            }
+            Err(err) => eprintln!("{}", diagnostic(line, &err)),
+        }
+    }
+}
+
+fn eval(context: &mut Context, program: &str, options: &Options) -> Result<Value, MexeError> {
+    if options.strict {
+        context.eval_program_strict(program)
+    } else {
+        context.eval_program(program)
+    }
+}
+
+fn value(val: Value, precision: Option<usize>) -> String {
+    match (val, precision) {
+        (Value::Number(n), Some(precision)) => format!("{:.*}", precision, n),
+        (val, _) => val.to_string(),
+    }
+}
+
+// The error message, followed by the line of the program with a caret under
+// the position of the error, if it has one.
+fn diagnostic(program: &str, err: &MexeError) -> String {
+    match err.line_column(program) {
+        Some((line, column)) if program.contains('\n') => format!(
+            "error: {} (line {}, column {})\n  {}\n  {:>column$}",
+            err,
+            line,
+            column,
+            program.lines().nth(line - 1).unwrap_or(""),
+            "^",
+        ),
+        Some((_, column)) => format!("error: {}\n  {}\n  {:>column$}", err, program, "^"),
+        None => format!("error: {}", err),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
@@ -0,0 +1,352 @@
+use crate::{context, Ast, MexeError, Operator, Result};
+
+// Expressions that need at most this many stack slots are evaluated on a
+// small stack, and the others on one of `MAX_STACK` slots. Deeper expressions
+// are not compiled, so evaluation never allocates.
+const SMALL_STACK: usize = 32;
+pub(crate) const MAX_STACK: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Instruction {
+    PushConst(u32),
+    LoadVar(u32),
+    Neg,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// An expression compiled to bytecode, for fast repeated evaluation.
+///
+/// Created with [`compile`] or from an [`Ast`]. Constants are stored once in
+/// a pool, subexpressions without variables are computed at compile time, and
+/// evaluation runs the instructions on a small stack machine.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let expr = mexe::compile("x * x + 2 * y - 1")?;
+/// assert_eq!(expr.vars(), ["x", "y"]);
+///
+/// for i in 0..10 {
+///     let x = i as f64;
+///     assert_eq!(expr.eval(&[x, 0.5])?, x * x);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    pub(crate) code: Vec<Instruction>,
+    pub(crate) consts: Vec<f64>,
+    pub(crate) vars: Vec<String>,
+    pub(crate) stack_size: usize,
+}
+
+impl Expr {
+    /// Names of the variables of the expression, in the order their values
+    /// must be passed to [`Expr::eval`]. The constant `pi` is not a variable.
+    pub fn vars(&self) -> &[String] {
+        &self.vars
+    }
+
+    /// Evaluates the expression, where `values[i]` is the value of the
+    /// variable `self.vars()[i]`.
+    ///
+    /// Evaluation does not allocate.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MexeError::UndefinedVariable`] if fewer values than
+    /// variables are passed.
+    pub fn eval(&self, values: &[f64]) -> Result<f64> {
+        if values.len() < self.vars.len() {
+            return Err(MexeError::UndefinedVariable(
+                self.vars[values.len()].clone(),
+            ));
+        }
+
+        if self.stack_size <= SMALL_STACK {
+            Ok(self.run(values, &mut [0.0; SMALL_STACK]))
+        } else {
+            Ok(self.run(values, &mut [0.0; MAX_STACK]))
+        }
+    }
+
+    fn run(&self, values: &[f64], stack: &mut [f64]) -> f64 {
+        let mut top = 0;
+
+        for instruction in self.code.iter() {
+            match *instruction {
+                Instruction::PushConst(i) => {
+                    stack[top] = self.consts[i as usize];
+                    top += 1;
+                }
+                Instruction::LoadVar(i) => {
+                    stack[top] = values[i as usize];
+                    top += 1;
+                }
+                Instruction::Neg => stack[top - 1] = -stack[top - 1],
+                Instruction::Add => {
+                    top -= 1;
+                    stack[top - 1] += stack[top];
+                }
+                Instruction::Sub => {
+                    top -= 1;
+                    stack[top - 1] -= stack[top];
+                }
+                Instruction::Mul => {
+                    top -= 1;
+                    stack[top - 1] *= stack[top];
+                }
+                Instruction::Div => {
+                    top -= 1;
+                    stack[top - 1] /= stack[top];
+                }
+            }
+        }
+
+        stack[0]
+    }
+
+    fn emit(&mut self, ast: &Ast) -> Result<()> {
+        match ast {
+            Ast::Number(n) => self.push_const(*n),
+            Ast::Var(name) => match context::constant(name) {
+                Some(val) => self.push_const(val),
+                None => {
+                    let index = match self.vars.iter().position(|var| var == name) {
+                        Some(index) => index,
+                        None => {
+                            self.vars.push(name.clone());
+                            self.vars.len() - 1
+                        }
+                    };
+
+                    self.code.push(Instruction::LoadVar(index as u32));
+                }
+            },
+            Ast::Neg(inner) => {
+                self.emit_folded(inner)?;
+                self.code.push(Instruction::Neg);
+            }
+            Ast::Not(_) => return Err(unsupported("!")),
+            Ast::BitNot(_) => return Err(unsupported("~")),
+            Ast::Cond(..) => return Err(unsupported("?")),
+            Ast::Binary(op, lhs, rhs) => {
+                let instruction = match op {
+                    Operator::Add => Instruction::Add,
+                    Operator::Sub => Instruction::Sub,
+                    Operator::Mul => Instruction::Mul,
+                    Operator::Div => Instruction::Div,
+                    op => return Err(unsupported(&op.to_string())),
+                };
+
+                self.emit_folded(lhs)?;
+                self.emit_folded(rhs)?;
+                self.code.push(instruction);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit_folded(&mut self, ast: &Ast) -> Result<()> {
+        match fold(ast) {
+            Some(val) => {
+                self.push_const(val);
+                Ok(())
+            }
+            None => self.emit(ast),
+        }
+    }
+
+    fn push_const(&mut self, val: f64) {
+        let index = match self
+            .consts
+            .iter()
+            .position(|c| c.to_bits() == val.to_bits())
+        {
+            Some(index) => index,
+            None => {
+                self.consts.push(val);
+                self.consts.len() - 1
+            }
+        };
+
+        self.code.push(Instruction::PushConst(index as u32));
+    }
+
+    fn max_depth(&self) -> usize {
+        let mut depth: usize = 0;
+        let mut max = 0;
+
+        for instruction in self.code.iter() {
+            match instruction {
+                Instruction::PushConst(_) | Instruction::LoadVar(_) => depth += 1,
+                Instruction::Neg => (),
+                _ => depth -= 1,
+            }
+
+            max = max.max(depth);
+        }
+
+        max
+    }
+}
+
+impl TryFrom<&Ast> for Expr {
+    type Error = MexeError;
+
+    /// Compiles a tree. Only arithmetic can be compiled, so this fails with
+    /// [`MexeError::UnsupportedOperator`] if the tree has comparison, logical
+    /// or bitwise operators, or conditionals, even if they could be computed
+    /// at compile time. It fails with [`MexeError::ExpressionTooDeep`] if the
+    /// evaluation would need more than 256 intermediate values at once.
+    fn try_from(ast: &Ast) -> Result<Expr> {
+        let mut expr = Expr {
+            code: Vec::new(),
+            consts: Vec::new(),
+            vars: Vec::new(),
+            stack_size: 0,
+        };
+
+        expr.emit_folded(ast)?;
+        expr.stack_size = expr.max_depth();
+
+        if expr.stack_size > MAX_STACK {
+            return Err(MexeError::ExpressionTooDeep(expr.stack_size));
+        }
+
+        Ok(expr)
+    }
+}
+
+fn unsupported(op: &str) -> MexeError {
+    MexeError::UnsupportedOperator(op.to_owned())
+}
+
+// Computes subexpressions without variables, in the same order as `eval`.
+// Only the operators of the VM are computed, so that an expression is
+// rejected the same way whether or not it has variables.
+fn fold(ast: &Ast) -> Option<f64> {
+    match ast {
+        Ast::Number(n) => Some(*n),
+        Ast::Var(name) => context::constant(name),
+        Ast::Not(_) | Ast::BitNot(_) | Ast::Cond(..) => None,
+        Ast::Neg(inner) => fold(inner).map(|val| -val),
+        Ast::Binary(op, lhs, rhs) => op.apply(fold(lhs)?, fold(rhs)?),
+    }
+}
+
+/// Parses an expression and compiles it to bytecode.
+///
+/// Accepts the same input as [`parse`](crate::parse). See [`Expr`].
+///
+/// `T`: type of the expression. Usually a `&str` or a `String`.
+///
+/// # Errors
+///
+/// This function will return [`MexeError::UnsupportedOperator`] if the
+/// expression has comparison, logical or bitwise operators or conditionals,
+/// [`MexeError::ExpressionTooDeep`] if it is too deeply nested, and other
+/// [`MexeError`]s if the input is not a valid arithmetic expression.
+pub fn compile<T>(expression: T) -> Result<Expr>
+where
+    T: AsRef<str>,
+{
+    Expr::try_from(&crate::parse(expression)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval;
+
+    #[test]
+    fn compiled_expressions_evaluate_like_eval() {
+        let exprs = [
+            "1",
+            "-(2)",
+            "(1 + (4 * 5)) / 2 - 3 * 0.7",
+            "-(0.15 + 0.15 + 0.15) * -(1 - (2 - (3 - (4 - 5))))",
+            "2 * pi",
+        ];
+
+        for expr in exprs.iter() {
+            assert_eq!(compile(expr).unwrap().eval(&[]), eval(expr), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn constants_are_folded_and_pooled() {
+        let expr = compile("x * (2 + 3) + 5 * y - x").unwrap();
+
+        assert_eq!(expr.consts, vec![5.0]);
+        assert_eq!(expr.vars(), ["x", "y"]);
+        assert_eq!(
+            expr.code,
+            vec![
+                Instruction::LoadVar(0),
+                Instruction::PushConst(0),
+                Instruction::Mul,
+                Instruction::PushConst(0),
+                Instruction::LoadVar(1),
+                Instruction::Mul,
+                Instruction::Add,
+                Instruction::LoadVar(0),
+                Instruction::Sub,
+            ]
+        );
+        assert_eq!(expr.eval(&[1.0, 2.0]), Ok(14.0));
+
+        let expr = compile("2 * π * r").unwrap();
+        assert_eq!(expr.vars(), ["r"]);
+        assert_eq!(expr.consts, vec![2.0 * std::f64::consts::PI]);
+    }
+
+    #[test]
+    fn only_arithmetic_is_compiled() {
+        let unsupported = |op: &str| Err(MexeError::UnsupportedOperator(op.to_owned()));
+
+        assert_eq!(compile("x + (1 < 2)"), unsupported("<"));
+        assert_eq!(compile("-(!x)"), unsupported("!"));
+        assert_eq!(compile("x * ~1"), unsupported("~"));
+        assert_eq!(compile("x << 1"), unsupported("<<"));
+        assert_eq!(compile("1 << 2"), unsupported("<<"));
+        assert_eq!(compile("2 * (1 ? 2 : 3)"), unsupported("?"));
+    }
+
+    #[test]
+    fn missing_values_are_reported() {
+        let expr = compile("a + b").unwrap();
+        assert_eq!(
+            expr.eval(&[1.0]),
+            Err(MexeError::UndefinedVariable("b".to_owned()))
+        );
+    }
+
+    #[test]
+    fn deep_expressions_use_a_larger_stack() {
+        // `1 - (1 - (... - x))`, built directly as parsing it would need a
+        // deeper call stack than tests have
+        let nested = |depth| {
+            (0..depth).fold(Ast::Var("x".to_owned()), |ast, _| {
+                Ast::binary(Operator::Sub, Ast::Number(1.0), ast)
+            })
+        };
+
+        let expr = Expr::try_from(&nested(2 * SMALL_STACK)).unwrap();
+        assert!(expr.stack_size > SMALL_STACK);
+        assert_eq!(expr.eval(&[3.0]), Ok(3.0));
+
+        let expr = Expr::try_from(&nested(MAX_STACK - 1)).unwrap();
+        assert_eq!(expr.stack_size, MAX_STACK);
+        assert_eq!(expr.eval(&[3.0]), Ok(-2.0));
+
+        assert_eq!(
+            Expr::try_from(&nested(MAX_STACK)),
+            Err(MexeError::ExpressionTooDeep(MAX_STACK + 1))
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use crate::parser;
+use crate::{eval, lex, Result, Syntax, Value};
+
+// Constants that can be used without being set, unless a variable with the
+// same name is set.
+const CONSTANTS: [(&str, f64); 1] = [("pi", std::f64::consts::PI)];
+
+pub(crate) fn constant(name: &str) -> Option<f64> {
+    CONSTANTS
+        .iter()
+        .find(|(constant, _)| *constant == name)
+        .map(|(_, val)| *val)
+}
+
+/// Values of variables, for evaluating expressions that use them.
+///
+/// Variables are written as in [`parse`](crate::parse), and the values set in
+/// the context (directly or by the assignments of a program) persist across
+/// evaluations. The constant `pi` can be used without being set.
+///
+/// Expressions evaluated with a context can also use comparison (`<`, `<=`,
+/// `>`, `>=`, `==`, `!=`) and logical (`&&`, `||`, `!`) operators, so their
+/// result is a [`Value`]: a number or a boolean.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// use mexe::Value;
+///
+/// let mut context = mexe::Context::new();
+/// context.set("qty", 12.0);
+/// context.set("price", context.eval("2 * 2")?);
+///
+/// assert_eq!(context.eval("qty * price")?, Value::Number(48.0));
+/// assert_eq!(context.eval("qty > 10 && price < 5")?, Value::Bool(true));
+/// assert_eq!(context.get("price"), Some(Value::Number(4.0)));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Context {
+    vars: HashMap<String, Value>,
+    syntax: Syntax,
+}
+
+impl Context {
+    /// Creates a context without variables.
+    pub fn new() -> Context {
+        Context::default()
+    }
+
+    /// Sets the value of a variable, replacing its previous value. The value
+    /// can be an `f64`, a `bool` or a [`Value`].
+    pub fn set<S: Into<String>, V: Into<Value>>(&mut self, name: S, value: V) {
+        self.vars.insert(name.into(), value.into());
+    }
+
+    /// Sets the [`Syntax`] of the expressions and programs evaluated with
+    /// this context.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), mexe::MexeError> {
+    /// use mexe::{Syntax, Value};
+    ///
+    /// let mut context = mexe::Context::new();
+    /// context.set_syntax(Syntax::new().apostrophes(true));
+    ///
+    /// assert_eq!(context.eval("2 * 1'500")?, Value::Number(3000.0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.syntax = syntax;
+    }
+
+    /// Value of a variable, if it was set.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.vars.get(name).copied()
+    }
+
+    // Value of a variable or, if it was not set, of a constant.
+    pub(crate) fn value(&self, name: &str) -> Option<Value> {
+        self.get(name).or_else(|| constant(name).map(Value::Number))
+    }
+
+    /// Evaluates an expression like [`eval`](crate::eval), using the values
+    /// of the variables in this context.
+    ///
+    /// `T`: type of the expression. Usually a `&str` or a `String`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`MexeError::UndefinedVariable`] if the
+    /// expression uses a variable that was not set,
+    /// [`MexeError::TypeMismatch`] if an operator is applied to a value of the
+    /// wrong type, such as a boolean added to a number, and other
+    /// [`MexeError`]s if the input is not a valid expression.
+    ///
+    /// [`MexeError`]: crate::MexeError
+    /// [`MexeError::UndefinedVariable`]: crate::MexeError::UndefinedVariable
+    /// [`MexeError::TypeMismatch`]: crate::MexeError::TypeMismatch
+    pub fn eval<T>(&self, expression: T) -> Result<Value>
+    where
+        T: AsRef<str>,
+    {
+        eval::eval_ast(&self.syntax.parse(expression)?, self, false)
+    }
+
+    /// Evaluates an expression like [`eval_strict`](crate::eval_strict),
+    /// using the values of the variables in this context.
+    ///
+    /// `T`: type of the expression. Usually a `&str` or a `String`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Context::eval`], and also returns
+    /// [`MexeError::DivisionByZero`](crate::MexeError::DivisionByZero) if the
+    /// expression divides by zero.
+    pub fn eval_strict<T>(&self, expression: T) -> Result<Value>
+    where
+        T: AsRef<str>,
+    {
+        eval::eval_ast(&self.syntax.parse(expression)?, self, true)
+    }
+
+    /// Evaluates a program like [`eval_program`](crate::eval_program), using
+    /// the values of the variables in this context. The variables assigned by
+    /// the program are kept in the context, unless the evaluation fails.
+    ///
+    /// `T`: type of the program. Usually a `&str` or a `String`.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), mexe::MexeError> {
+    /// use mexe::Value;
+    ///
+    /// let mut context = mexe::Context::new();
+    /// context.set("price", 20.0);
+    ///
+    /// let total = context.eval_program("tax = 0.25; price * (1 + tax)")?;
+    /// assert_eq!(total, Value::Number(25.0));
+    /// assert_eq!(context.get("tax"), Some(Value::Number(0.25)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Same as [`eval_program`](crate::eval_program), except that variables
+    /// set in this context are defined.
+    pub fn eval_program<T>(&mut self, program: T) -> Result<Value>
+    where
+        T: AsRef<str>,
+    {
+        self.run(program.as_ref(), false)
+    }
+
+    /// Evaluates a program like [`Context::eval_program`], but division by
+    /// zero is an error, as in [`eval_strict`](crate::eval_strict).
+    ///
+    /// `T`: type of the program. Usually a `&str` or a `String`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Context::eval_program`], and also returns
+    /// [`MexeError::DivisionByZero`](crate::MexeError::DivisionByZero) if the
+    /// program divides by zero.
+    pub fn eval_program_strict<T>(&mut self, program: T) -> Result<Value>
+    where
+        T: AsRef<str>,
+    {
+        self.run(program.as_ref(), true)
+    }
+
+    fn run(&mut self, program: &str, strict: bool) -> Result<Value> {
+        let tokens = lex::get_program_tokens(program, &self.syntax)?;
+        let statements = parser::parse_program(&tokens, |name| self.value(name).is_some())
+            .map_err(|err| lex::parse_error(program, &self.syntax, true, &tokens, err))?;
+
+        let mut scope = self.clone();
+        let mut result = Value::Number(0.0);
+
+        for (name, ast) in statements.iter() {
+            result = eval::eval_ast(ast, &scope, strict)?;
+
+            if let Some(name) = name {
+                scope.set(*name, result);
+            }
+        }
+
+        *self = scope;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MexeError, NumberFormat};
+
+    #[test]
+    fn variables_are_replaced_by_their_values() {
+        let mut context = Context::new();
+        context.set("x", 2.0);
+        context.set("long_name_1", 0.5);
+
+        assert_eq!(
+            context.eval("x * (long_name_1 - x)"),
+            Ok(Value::Number(-3.0))
+        );
+        assert_eq!(context.eval("-x / -x"), Ok(Value::Number(1.0)));
+
+        context.set("x", 4.0);
+        assert_eq!(context.eval("x"), Ok(Value::Number(4.0)));
+        assert_eq!(
+            context.eval("x + y"),
+            Err(MexeError::UndefinedVariable("y".to_owned()))
+        );
+        assert_eq!(
+            context.eval("x +"),
+            Err(MexeError::UnexpectedToken("EOI".to_owned(), 3))
+        );
+    }
+
+    #[test]
+    fn constants_can_be_shadowed() {
+        let mut context = Context::new();
+        assert_eq!(context.eval("pi"), Ok(Value::Number(std::f64::consts::PI)));
+        assert_eq!(context.get("pi"), None);
+
+        context.set("pi", 3.0);
+        assert_eq!(context.eval("pi"), Ok(Value::Number(3.0)));
+        assert_eq!(context.eval("2·π"), Ok(Value::Number(6.0)));
+    }
+
+    #[test]
+    fn programs_assign_variables() {
+        let mut context = Context::new();
+        context.set("r", 1.0);
+
+        assert_eq!(
+            context.eval_program("r = r + 1\nd = 2 * r;"),
+            Ok(Value::Number(4.0))
+        );
+        assert_eq!(context.get("r"), Some(Value::Number(2.0)));
+        assert_eq!(context.get("d"), Some(Value::Number(4.0)));
+
+        // nothing is assigned if the program fails
+        assert_eq!(
+            context.eval_program_strict("r = 5; d / 0"),
+            Err(MexeError::DivisionByZero)
+        );
+        assert_eq!(context.get("r"), Some(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn strict_evaluation() {
+        let mut context = Context::new();
+        context.set("zero", 0.0);
+
+        assert_eq!(context.eval("1 / zero"), Ok(Value::Number(f64::INFINITY)));
+        assert_eq!(
+            context.eval_strict("1 / zero"),
+            Err(MexeError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn booleans() {
+        let mut context = Context::new();
+        context.set("qty", 12.0);
+        context.set("vip", false);
+
+        assert_eq!(context.eval("qty > 10 && !vip"), Ok(Value::Bool(true)));
+        assert_eq!(
+            context.eval_program("bulk = qty >= 10; bulk || vip"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(context.get("bulk"), Some(Value::Bool(true)));
+        assert_eq!(
+            context.eval("qty + vip"),
+            Err(MexeError::TypeMismatch(
+                "`+` cannot be applied to a boolean".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn syntax_is_used_by_programs() {
+        let mut context = Context::new();
+        context.set_syntax(Syntax::new().number_format(NumberFormat::DecimalComma));
+
+        assert_eq!(
+            context.eval_program("x = 2,5; if(x > 1; x * 2; 0)"),
+            Ok(Value::Number(5.0))
+        );
+        assert_eq!(
+            context.eval_program("if(x; 1)"),
+            Err(MexeError::UnexpectedToken(")".to_owned(), 7))
+        );
+        assert_eq!(
+            context.eval("(1; 2)"),
+            Err(MexeError::UnexpectedToken(";".to_owned(), 2))
+        );
+    }
+}
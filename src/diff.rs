@@ -0,0 +1,95 @@
+use crate::simplify::simplify;
+use crate::{Ast, Operator};
+
+/// Differentiates an expression with respect to the variable `var`.
+///
+/// The sum, product and quotient rules are applied recursively, and the
+/// result is passed through [`simplify`](crate::simplify). Other variables are
+/// treated as constants, and comparisons and logical operators, which are
+/// piecewise constant, have a derivative of `0`. The derivative of a
+/// conditional is the conditional of the derivatives of its branches.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let ast = mexe::parse("x * x + 3 * x")?;
+/// let derivative = mexe::differentiate(&ast, "x");
+///
+/// assert_eq!(derivative.to_string(), "2 * x + 3");
+/// # Ok(())
+/// # }
+/// ```
+pub fn differentiate(ast: &Ast, var: &str) -> Ast {
+    simplify(&derive(ast, var))
+}
+
+fn derive(ast: &Ast, var: &str) -> Ast {
+    match ast {
+        Ast::Number(_) => Ast::Number(0.0),
+        Ast::Var(name) if name == var => Ast::Number(1.0),
+        Ast::Var(_) => Ast::Number(0.0),
+        Ast::Neg(inner) => Ast::neg(derive(inner, var)),
+        Ast::Binary(op @ (Operator::Add | Operator::Sub), lhs, rhs) => {
+            Ast::binary(*op, derive(lhs, var), derive(rhs, var))
+        }
+        // (fg)' = f'g + fg'
+        Ast::Binary(Operator::Mul, lhs, rhs) => Ast::binary(
+            Operator::Add,
+            Ast::binary(Operator::Mul, derive(lhs, var), *rhs.clone()),
+            Ast::binary(Operator::Mul, *lhs.clone(), derive(rhs, var)),
+        ),
+        // (f/g)' = (f'g - fg') / g²
+        Ast::Binary(Operator::Div, lhs, rhs) => Ast::binary(
+            Operator::Div,
+            Ast::binary(
+                Operator::Sub,
+                Ast::binary(Operator::Mul, derive(lhs, var), *rhs.clone()),
+                Ast::binary(Operator::Mul, *lhs.clone(), derive(rhs, var)),
+            ),
+            Ast::binary(Operator::Mul, *rhs.clone(), *rhs.clone()),
+        ),
+        Ast::Cond(cond, then, otherwise) => {
+            Ast::cond(*cond.clone(), derive(then, var), derive(otherwise, var))
+        }
+        // comparisons and logical operators are piecewise constant, and the
+        // bitwise ones are only defined on integers
+        Ast::Not(_) | Ast::BitNot(_) | Ast::Binary(..) => Ast::Number(0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn d(expr: &str) -> String {
+        differentiate(&parse(expr).unwrap(), "x").to_string()
+    }
+
+    #[test]
+    fn test_differentiate() {
+        assert_eq!(d("7"), "0");
+        assert_eq!(d("x"), "1");
+        assert_eq!(d("y"), "0");
+        assert_eq!(d("-x"), "-1");
+        assert_eq!(d("x + y"), "1");
+        assert_eq!(d("y - x"), "-1");
+        assert_eq!(d("3 * x"), "3");
+        assert_eq!(d("x * y"), "y");
+        assert_eq!(d("x * x"), "2 * x");
+        assert_eq!(d("-(x * x) / 2"), "-x");
+        assert_eq!(d("1 / x"), "-1 / (x * x)");
+        assert_eq!(d("x / y"), "1 / y");
+        assert_eq!(d("x < 1 || !(x * x == y)"), "0");
+        assert_eq!(d("x < 0 ? -x : x * x"), "x < 0 ? -1 : 2 * x");
+    }
+
+    #[test]
+    fn derivative_can_be_parsed_back() {
+        let exprs = ["(x + 1) / (x - 1)", "-(x * y) * -x", "x / (2 * x * x)"];
+
+        for expr in exprs.iter() {
+            let derivative = d(expr);
+            assert!(parse(&derivative).is_ok(), "{}", derivative);
+        }
+    }
+}
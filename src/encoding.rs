@@ -0,0 +1,299 @@
+use crate::bytecode::{Instruction, MAX_STACK};
+use crate::{Expr, MexeError, Result};
+
+const MAGIC: &[u8; 4] = b"MEXE";
+const VERSION: u16 = 1;
+
+const OP_PUSH_CONST: u8 = 0;
+const OP_LOAD_VAR: u8 = 1;
+const OP_NEG: u8 = 2;
+const OP_ADD: u8 = 3;
+const OP_SUB: u8 = 4;
+const OP_MUL: u8 = 5;
+const OP_DIV: u8 = 6;
+
+impl Expr {
+    /// Encodes the compiled expression in a stable binary format, which can be
+    /// decoded with [`Expr::from_bytes`].
+    ///
+    /// All integers and floats are little-endian. The format (version 1) is:
+    ///
+    /// - the magic bytes `MEXE` and the format version as a `u16`
+    /// - the number of constants (`u32`), followed by the constants (`f64`)
+    /// - the number of variables (`u32`), followed by the variable names, each
+    ///   as its length in bytes (`u32`) and its UTF-8 bytes
+    /// - the number of instructions (`u32`), followed by the instructions,
+    ///   each as an opcode byte, plus a `u32` index into the constants or
+    ///   variables for the push constant (`0`) and load variable (`1`)
+    ///   instructions. The other opcodes are negate (`2`), add (`3`),
+    ///   subtract (`4`), multiply (`5`) and divide (`6`).
+    ///
+    /// ```
+    /// # fn main() -> Result<(), mexe::MexeError> {
+    /// let expr = mexe::compile("2 * x - 1")?;
+    /// let bytes = expr.to_bytes();
+    ///
+    /// assert_eq!(mexe::Expr::from_bytes(&bytes)?, expr);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+
+        bytes.extend_from_slice(&(self.consts.len() as u32).to_le_bytes());
+        for c in self.consts.iter() {
+            bytes.extend_from_slice(&c.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.vars.len() as u32).to_le_bytes());
+        for var in self.vars.iter() {
+            bytes.extend_from_slice(&(var.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(var.as_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.code.len() as u32).to_le_bytes());
+        for instruction in self.code.iter() {
+            match *instruction {
+                Instruction::PushConst(i) => {
+                    bytes.push(OP_PUSH_CONST);
+                    bytes.extend_from_slice(&i.to_le_bytes());
+                }
+                Instruction::LoadVar(i) => {
+                    bytes.push(OP_LOAD_VAR);
+                    bytes.extend_from_slice(&i.to_le_bytes());
+                }
+                Instruction::Neg => bytes.push(OP_NEG),
+                Instruction::Add => bytes.push(OP_ADD),
+                Instruction::Sub => bytes.push(OP_SUB),
+                Instruction::Mul => bytes.push(OP_MUL),
+                Instruction::Div => bytes.push(OP_DIV),
+            }
+        }
+
+        bytes
+    }
+
+    /// Decodes a compiled expression encoded by [`Expr::to_bytes`].
+    ///
+    /// The input is fully validated, so that corrupt or malicious data results
+    /// in an error, and evaluating a decoded expression cannot panic.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MexeError::UnsupportedVersion`] if the data was encoded with
+    /// a format version that this version of the library cannot read, and
+    /// [`MexeError::InvalidBytecode`], with the offset of the offending byte,
+    /// if the data is not a valid compiled expression.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Expr> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(MexeError::InvalidBytecode(0));
+        }
+
+        let version = u16::from_le_bytes(reader.array()?);
+        if version != VERSION {
+            return Err(MexeError::UnsupportedVersion(version));
+        }
+
+        let len = reader.len(8)?;
+        let mut consts = Vec::with_capacity(len);
+        for _ in 0..len {
+            consts.push(f64::from_le_bytes(reader.array()?));
+        }
+
+        let len = reader.len(4)?;
+        let mut vars = Vec::with_capacity(len);
+        for _ in 0..len {
+            let start = reader.pos;
+            let var_len = reader.len(1)?;
+            let name = std::str::from_utf8(reader.take(var_len)?)
+                .map_err(|_| MexeError::InvalidBytecode(start))?;
+            vars.push(name.to_owned());
+        }
+
+        let len = reader.len(1)?;
+        let mut code = Vec::with_capacity(len);
+        let mut depth: usize = 0;
+        let mut stack_size = 0;
+
+        for _ in 0..len {
+            let start = reader.pos;
+            let instruction = match reader.take(1)?[0] {
+                OP_PUSH_CONST => Instruction::PushConst(reader.index(consts.len())?),
+                OP_LOAD_VAR => Instruction::LoadVar(reader.index(vars.len())?),
+                OP_NEG => Instruction::Neg,
+                OP_ADD => Instruction::Add,
+                OP_SUB => Instruction::Sub,
+                OP_MUL => Instruction::Mul,
+                OP_DIV => Instruction::Div,
+                _ => return Err(MexeError::InvalidBytecode(start)),
+            };
+
+            // the instructions must never pop from an empty stack, nor push
+            // more values than the stack of the VM holds
+            depth = match instruction {
+                Instruction::PushConst(_) | Instruction::LoadVar(_) if depth < MAX_STACK => {
+                    depth + 1
+                }
+                Instruction::Neg if depth >= 1 => depth,
+                Instruction::Add | Instruction::Sub | Instruction::Mul | Instruction::Div
+                    if depth >= 2 =>
+                {
+                    depth - 1
+                }
+                _ => return Err(MexeError::InvalidBytecode(start)),
+            };
+
+            stack_size = stack_size.max(depth);
+            code.push(instruction);
+        }
+
+        // exactly one value must be left: the result
+        if depth != 1 || reader.pos != bytes.len() {
+            return Err(MexeError::InvalidBytecode(reader.pos));
+        }
+
+        Ok(Expr {
+            code,
+            consts,
+            vars,
+            stack_size,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        match self.bytes.get(self.pos..self.pos.saturating_add(len)) {
+            Some(bytes) => {
+                self.pos += len;
+                Ok(bytes)
+            }
+            None => Err(MexeError::InvalidBytecode(self.bytes.len())),
+        }
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    // Reads a length, checking that the remaining input can hold that many
+    // items of at least `item_size` bytes, to avoid huge allocations.
+    fn len(&mut self, item_size: usize) -> Result<usize> {
+        let start = self.pos;
+        let len = u32::from_le_bytes(self.array()?) as usize;
+
+        match len.checked_mul(item_size) {
+            Some(size) if size <= self.bytes.len() - self.pos => Ok(len),
+            _ => Err(MexeError::InvalidBytecode(start)),
+        }
+    }
+
+    fn index(&mut self, bound: usize) -> Result<u32> {
+        let start = self.pos;
+        let index = u32::from_le_bytes(self.array()?);
+
+        if (index as usize) < bound {
+            Ok(index)
+        } else {
+            Err(MexeError::InvalidBytecode(start))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile;
+
+    #[test]
+    fn encoding_round_trip() {
+        let exprs = ["1", "-x", "(a + 2) * (b - 2) / -(a * c)"];
+
+        for expr in exprs.iter() {
+            let expr = compile(expr).unwrap();
+            let decoded = Expr::from_bytes(&expr.to_bytes()).unwrap();
+
+            assert_eq!(decoded, expr);
+            assert_eq!(decoded.eval(&[1.0, 2.0, 3.0]), expr.eval(&[1.0, 2.0, 3.0]));
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        let bytes = compile("x - 0.5").unwrap().to_bytes();
+        let mut expected = b"MEXE\x01\x00".to_vec();
+        expected.extend_from_slice(&[1, 0, 0, 0]);
+        expected.extend_from_slice(&0.5f64.to_le_bytes());
+        expected.extend_from_slice(&[1, 0, 0, 0, 1, 0, 0, 0, b'x']);
+        expected.extend_from_slice(&[3, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4]);
+
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn invalid_bytes_are_rejected() {
+        let valid = compile("x - 0.5").unwrap().to_bytes();
+        let with = |pos: usize, byte: u8| {
+            let mut bytes = valid.clone();
+            bytes[pos] = byte;
+            Expr::from_bytes(&bytes)
+        };
+
+        assert_eq!(Expr::from_bytes(b""), Err(MexeError::InvalidBytecode(0)));
+        assert_eq!(with(0, b'X'), Err(MexeError::InvalidBytecode(0)));
+        assert_eq!(with(4, 2), Err(MexeError::UnsupportedVersion(2)));
+        // huge number of constants
+        assert_eq!(with(9, 0xff), Err(MexeError::InvalidBytecode(6)));
+        // invalid UTF-8 in the variable name
+        assert_eq!(with(26, 0xff), Err(MexeError::InvalidBytecode(22)));
+        // variable index out of range
+        assert_eq!(with(32, 1), Err(MexeError::InvalidBytecode(32)));
+        // unknown opcode
+        assert_eq!(with(41, 7), Err(MexeError::InvalidBytecode(41)));
+        // subtraction with a single value on the stack
+        let mut bytes = b"MEXE\x01\x00".to_vec();
+        bytes.extend_from_slice(&[1, 0, 0, 0]);
+        bytes.extend_from_slice(&0.5f64.to_le_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0, 2, 0, 0, 0, OP_PUSH_CONST, 0, 0, 0, 0, OP_SUB]);
+        assert_eq!(
+            Expr::from_bytes(&bytes),
+            Err(MexeError::InvalidBytecode(31))
+        );
+
+        // more values than the stack holds
+        let mut bytes = b"MEXE\x01\x00".to_vec();
+        bytes.extend_from_slice(&[1, 0, 0, 0]);
+        bytes.extend_from_slice(&0.5f64.to_le_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        bytes.extend_from_slice(&(MAX_STACK as u32 + 1).to_le_bytes());
+        for _ in 0..=MAX_STACK {
+            bytes.extend_from_slice(&[OP_PUSH_CONST, 0, 0, 0, 0]);
+        }
+        assert_eq!(
+            Expr::from_bytes(&bytes),
+            Err(MexeError::InvalidBytecode(26 + 5 * MAX_STACK))
+        );
+
+        let mut bytes = valid.clone();
+        bytes.push(OP_NEG);
+        assert_eq!(
+            Expr::from_bytes(&bytes),
+            Err(MexeError::InvalidBytecode(valid.len()))
+        );
+
+        for len in 0..valid.len() {
+            assert!(Expr::from_bytes(&valid[..len]).is_err());
+        }
+    }
+}
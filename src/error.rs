@@ -1,5 +1,7 @@
 /// Represents any errors that may occur in this library
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum MexeError {
     /// Invalid character at the specified index
     InvalidCharacter(usize),
@@ -7,14 +9,108 @@ pub enum MexeError {
     /// Unexpected character (u8) at index
     UnexpectedCharacter(u8, usize),
 
+    /// Character (u8) at index that is not a digit in the base of the
+    /// number, such as the `2` in `0b102`
+    InvalidDigit(u8, usize),
+
+    /// Digit separator at index that is not between two digits of a number,
+    /// such as the second `_` in `1__000`
+    MisplacedSeparator(usize),
+
+    /// Block comment without its closing `*/`, with the index of its
+    /// opening `/*`
+    UnterminatedComment(usize),
+
+    /// Literal at index that an `f64` cannot hold: an integer in another
+    /// base, such as `0x20000000000001`, above 2^53, the largest integer up to
+    /// which every integer is an exact `f64`, or a decimal number too large to
+    /// be finite
+    LiteralTooLarge(usize),
+
     /// Binary expression should be: number operator number
     InvalidBinaryExpression,
     MissingOperand,
     MissingOperator,
-    /// Unexpected token
-    UnexpectedToken(String),
+    /// Unexpected token, with the index where it starts, or the length of the
+    /// expression for the end of input
+    UnexpectedToken(String, usize),
     InternalParserError,
     UnexpectEndOfInput,
+    /// A variable without a value
+    UndefinedVariable(String),
+    /// Compiled expression encoded with an unsupported format version
+    UnsupportedVersion(u16),
+    /// Invalid compiled expression, with the offset of the offending byte
+    InvalidBytecode(usize),
+    /// Operator that compiled expressions do not support, such as `<`
+    UnsupportedOperator(String),
+    /// Expression too deeply nested to be compiled, with the number of stack
+    /// slots it would need
+    ExpressionTooDeep(usize),
+    /// Division by zero in strict mode
+    DivisionByZero,
+    /// Variable used in a program before being assigned, with the index
+    /// where it is used
+    UseBeforeDefinition(String, usize),
+    /// Operation applied to a value of the wrong type, such as adding a
+    /// boolean to a number
+    TypeMismatch(String),
+}
+
+impl MexeError {
+    /// Index of the character of the expression where the error was found,
+    /// if the error carries the position where it was found, which is useful
+    /// for showing a caret under the offending part of the input. Characters
+    /// are counted, not bytes.
+    ///
+    /// ```
+    /// let err = mexe::eval("1 + $ 2").unwrap_err();
+    /// assert_eq!(err.index(), Some(4));
+    ///
+    /// let err = mexe::eval("2 × 3 ÷ $ 4").unwrap_err();
+    /// assert_eq!(err.index(), Some(8));
+    ///
+    /// let err = mexe::eval("(1 + 2").unwrap_err();
+    /// assert_eq!(err.index(), Some(6));
+    /// ```
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            MexeError::InvalidCharacter(index)
+            | MexeError::UnexpectedCharacter(_, index)
+            | MexeError::InvalidDigit(_, index)
+            | MexeError::MisplacedSeparator(index)
+            | MexeError::UnterminatedComment(index)
+            | MexeError::LiteralTooLarge(index)
+            | MexeError::UnexpectedToken(_, index)
+            | MexeError::UseBeforeDefinition(_, index) => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Line and column where the error was found in `expression`, both
+    /// starting at 1, if the error carries the position where it was found
+    /// (see [`MexeError::index`]).
+    ///
+    /// ```
+    /// let expression = "1 +\n\t(2 $)";
+    /// let err = mexe::eval(expression).unwrap_err();
+    /// assert_eq!(err.index(), Some(8));
+    /// assert_eq!(err.line_column(expression), Some((2, 5)));
+    /// ```
+    pub fn line_column(&self, expression: &str) -> Option<(usize, usize)> {
+        let index = self.index()?;
+        let mut chars = expression.chars();
+        let (mut line, mut column) = (1, 1);
+
+        for _ in 0..index {
+            match chars.next()? {
+                '\n' => (line, column) = (line + 1, 1),
+                _ => column += 1,
+            }
+        }
+
+        Some((line, column))
+    }
 }
 
 impl std::error::Error for MexeError {}
@@ -26,12 +122,48 @@ impl std::fmt::Display for MexeError {
             MexeError::UnexpectedCharacter(_character, index) => {
                 write!(f, "Unexpected character at index {}", index)
             }
+            MexeError::InvalidDigit(digit, index) => {
+                write!(f, "Invalid digit `{}` at index {}", *digit as char, index)
+            }
+            MexeError::MisplacedSeparator(index) => {
+                write!(f, "Misplaced digit separator at index {}", index)
+            }
+            MexeError::UnterminatedComment(index) => {
+                write!(f, "Unterminated comment starting at index {}", index)
+            }
+            MexeError::LiteralTooLarge(index) => {
+                write!(f, "Literal too large at index {}", index)
+            }
             MexeError::InvalidBinaryExpression => write!(f, "Invalid binary expression"),
             MexeError::MissingOperand => write!(f, "Missing operand"),
             MexeError::MissingOperator => write!(f, "Missing operator"),
-            MexeError::UnexpectedToken(token) => write!(f, "Unexpected token: `{}`", token),
+            MexeError::UnexpectedToken(token, index) => {
+                write!(f, "Unexpected token: `{}` at index {}", token, index)
+            }
             MexeError::InternalParserError => write!(f, "Internal parser error"),
             MexeError::UnexpectEndOfInput => write!(f, "Unexpected end of input"),
+            MexeError::UndefinedVariable(name) => write!(f, "Undefined variable: `{}`", name),
+            MexeError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported compiled expression version {}", version)
+            }
+            MexeError::InvalidBytecode(offset) => {
+                write!(f, "Invalid compiled expression at byte {}", offset)
+            }
+            MexeError::UnsupportedOperator(op) => {
+                write!(f, "`{}` is not supported in compiled expressions", op)
+            }
+            MexeError::ExpressionTooDeep(size) => write!(
+                f,
+                "Expression needs {} stack slots, more than compiled expressions support",
+                size
+            ),
+            MexeError::DivisionByZero => write!(f, "Division by zero"),
+            MexeError::UseBeforeDefinition(name, index) => write!(
+                f,
+                "Variable `{}` used before being defined at index {}",
+                name, index
+            ),
+            MexeError::TypeMismatch(message) => write!(f, "Type mismatch: {}", message),
         }
     }
 }
@@ -0,0 +1,310 @@
+use crate::{Ast, Context, MexeError, Operator, Result, Value};
+
+// Evaluates a tree, with the values of the variables taken from `context`. In
+// strict mode, division by zero is an error instead of resulting in infinity
+// or NaN. Only the branch of a conditional that is taken is evaluated.
+pub(crate) fn eval_ast(ast: &Ast, context: &Context, strict: bool) -> Result<Value> {
+    match ast {
+        Ast::Number(n) => Ok(Value::Number(*n)),
+        Ast::Var(name) => context
+            .value(name)
+            .ok_or_else(|| MexeError::UndefinedVariable(name.clone())),
+        Ast::Neg(inner) => negate(eval_ast(inner, context, strict)?),
+        Ast::Not(inner) => logical_not(eval_ast(inner, context, strict)?),
+        Ast::BitNot(inner) => bitwise_not(eval_ast(inner, context, strict)?),
+        Ast::Cond(cond, then, otherwise) => match condition(eval_ast(cond, context, strict)?)? {
+            true => eval_ast(then, context, strict),
+            false => eval_ast(otherwise, context, strict),
+        },
+        Ast::Binary(op, lhs, rhs) => {
+            let lhs = eval_ast(lhs, context, strict)?;
+
+            match short_circuit(*op, lhs) {
+                Some(result) => result,
+                None => binary(*op, lhs, eval_ast(rhs, context, strict)?, strict),
+            }
+        }
+    }
+}
+
+pub(crate) fn negate(val: Value) -> Result<Value> {
+    match val {
+        Value::Number(n) => Ok(Value::Number(-n)),
+        Value::Bool(_) => Err(mismatch("-", "a boolean")),
+    }
+}
+
+pub(crate) fn logical_not(val: Value) -> Result<Value> {
+    match val {
+        Value::Bool(b) => Ok(Value::Bool(!b)),
+        Value::Number(_) => Err(mismatch("!", "a number")),
+    }
+}
+
+pub(crate) fn bitwise_not(val: Value) -> Result<Value> {
+    match val {
+        Value::Number(n) => Ok(Value::Number(bit_not(n)?)),
+        Value::Bool(_) => Err(mismatch("~", "a boolean")),
+    }
+}
+
+// Whether the condition of a conditional holds.
+pub(crate) fn condition(val: Value) -> Result<bool> {
+    match val {
+        Value::Bool(b) => Ok(b),
+        Value::Number(_) => Err(MexeError::TypeMismatch(
+            "a condition cannot be a number".to_owned(),
+        )),
+    }
+}
+
+// The result of `&&` and `||` when it does not depend on the right operand,
+// which is then not evaluated, or `None` if it does. `None` for the other
+// operators.
+#[inline]
+pub(crate) fn short_circuit(op: Operator, lhs: Value) -> Option<Result<Value>> {
+    match (op, lhs) {
+        (Operator::And, Value::Bool(false)) => Some(Ok(Value::Bool(false))),
+        (Operator::Or, Value::Bool(true)) => Some(Ok(Value::Bool(true))),
+        (Operator::And | Operator::Or, Value::Number(_)) => {
+            Some(Err(mismatch(&op.to_string(), "a number")))
+        }
+        _ => None,
+    }
+}
+
+// Applies a binary operator. For `&&` and `||`, `lhs` is the one that did not
+// short-circuit, see `short_circuit`.
+pub(crate) fn binary(op: Operator, lhs: Value, rhs: Value, strict: bool) -> Result<Value> {
+    match (op, lhs, rhs) {
+        (Operator::And | Operator::Or, _, Value::Bool(b)) => Ok(Value::Bool(b)),
+        (Operator::And | Operator::Or, _, Value::Number(_)) => {
+            Err(mismatch(&op.to_string(), "a number"))
+        }
+        (Operator::Eq, lhs, rhs) if same_type(lhs, rhs) => Ok(Value::Bool(lhs == rhs)),
+        (Operator::Ne, lhs, rhs) if same_type(lhs, rhs) => Ok(Value::Bool(lhs != rhs)),
+        (Operator::Eq | Operator::Ne, _, _) => Err(MexeError::TypeMismatch(format!(
+            "`{}` cannot compare a number with a boolean",
+            op
+        ))),
+        (_, Value::Number(lhs), Value::Number(rhs)) => Ok(match op {
+            Operator::Add => Value::Number(lhs + rhs),
+            Operator::Sub => Value::Number(lhs - rhs),
+            Operator::Mul => Value::Number(lhs * rhs),
+            Operator::Div if strict && rhs == 0.0 => return Err(MexeError::DivisionByZero),
+            Operator::Div => Value::Number(lhs / rhs),
+            Operator::Lt => Value::Bool(lhs < rhs),
+            Operator::Le => Value::Bool(lhs <= rhs),
+            Operator::Gt => Value::Bool(lhs > rhs),
+            Operator::BitAnd
+            | Operator::BitOr
+            | Operator::BitXor
+            | Operator::Shl
+            | Operator::Shr => Value::Number(bitwise(op, lhs, rhs)?),
+            // the other operators were matched above
+            _ => Value::Bool(lhs >= rhs),
+        }),
+        _ => Err(mismatch(&op.to_string(), "a boolean")),
+    }
+}
+
+// Applies a bitwise or shift operator. The operands must be integers that fit
+// in an `i64`, and shift amounts must be between 0 and 63. `>>` is an
+// arithmetic shift, so it keeps the sign.
+fn bitwise(op: Operator, lhs: f64, rhs: f64) -> Result<f64> {
+    let name = op.to_string();
+    let lhs = integer(&name, lhs)?;
+    let rhs = integer(&name, rhs)?;
+
+    let val = match op {
+        Operator::BitAnd => lhs & rhs,
+        Operator::BitOr => lhs | rhs,
+        Operator::BitXor => lhs ^ rhs,
+        Operator::Shl | Operator::Shr if !(0..64).contains(&rhs) => {
+            return Err(MexeError::TypeMismatch(format!(
+                "`{}` cannot shift by {}, which is not between 0 and 63",
+                op, rhs
+            )))
+        }
+        Operator::Shl => lhs << rhs,
+        Operator::Shr => lhs >> rhs,
+        _ => unreachable!(),
+    };
+
+    Ok(val as f64)
+}
+
+fn bit_not(n: f64) -> Result<f64> {
+    Ok(!integer("~", n)? as f64)
+}
+
+fn integer(op: &str, n: f64) -> Result<i64> {
+    // 2^63 is the first value that does not fit
+    if n.fract() == 0.0 && (-9223372036854775808.0..9223372036854775808.0).contains(&n) {
+        Ok(n as i64)
+    } else {
+        Err(MexeError::TypeMismatch(format!(
+            "`{}` cannot be applied to {}, which is not a 64-bit integer",
+            op, n
+        )))
+    }
+}
+
+fn same_type(lhs: Value, rhs: Value) -> bool {
+    matches!(
+        (lhs, rhs),
+        (Value::Number(_), Value::Number(_)) | (Value::Bool(_), Value::Bool(_))
+    )
+}
+
+fn mismatch(op: &str, operand: &str) -> MexeError {
+    MexeError::TypeMismatch(format!("`{}` cannot be applied to {}", op, operand))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{eval, parse};
+
+    fn eval_ast(ast: &Ast, strict: bool) -> Result<Value> {
+        super::eval_ast(ast, &Context::new(), strict)
+    }
+
+    #[test]
+    fn evaluates_like_eval() {
+        let exprs = [
+            "1",
+            "-(2)",
+            "(1 + (4 * 5)) / 2 - 3 * 0.7",
+            "-(0.15 + 0.15 + 0.15) * -(1 - (2 - (3 - (4 - 5))))",
+            "1 / 0",
+        ];
+
+        for expr in exprs.iter() {
+            assert_eq!(
+                eval_ast(&parse(expr).unwrap(), false),
+                eval(expr).map(Value::Number),
+                "{}",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn strict_mode_rejects_division_by_zero() {
+        let ast = parse("1 / (2 - 2)").unwrap();
+
+        assert_eq!(eval_ast(&ast, false), Ok(Value::Number(f64::INFINITY)));
+        assert_eq!(eval_ast(&ast, true), Err(MexeError::DivisionByZero));
+        assert_eq!(
+            eval_ast(&parse("0 / -0").unwrap(), true),
+            Err(MexeError::DivisionByZero)
+        );
+        assert_eq!(
+            eval_ast(&parse("x").unwrap(), true),
+            Err(MexeError::UndefinedVariable("x".to_owned()))
+        );
+    }
+
+    #[test]
+    fn comparisons_and_logical_operators() {
+        let eval = |expr| eval_ast(&parse(expr).unwrap(), false);
+
+        assert_eq!(eval("1 + 1 == 2"), Ok(Value::Bool(true)));
+        assert_eq!(eval("2 * 3 <= 5 || 1 != 1"), Ok(Value::Bool(false)));
+        assert_eq!(eval("3 > 2 && !(2 >= 3)"), Ok(Value::Bool(true)));
+        assert_eq!(eval("(1 < 2) == (2 < 1)"), Ok(Value::Bool(false)));
+        assert_eq!(eval("!!(0 / 0 != 0 / 0)"), Ok(Value::Bool(true)));
+        // the right operand is not evaluated
+        assert_eq!(eval("1 > 2 && x"), Ok(Value::Bool(false)));
+        assert_eq!(eval("1 < 2 || x"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn only_the_branch_taken_is_evaluated() {
+        let mut context = Context::new();
+        context.set("x", 0.0);
+        let eval = |expr| super::eval_ast(&parse(expr).unwrap(), &context, true);
+
+        assert_eq!(eval("x == 0 ? 0 : 1 / x"), Ok(Value::Number(0.0)));
+        assert_eq!(
+            eval("if(x != 0, 1 / x, y)"),
+            Err(MexeError::UndefinedVariable("y".to_owned()))
+        );
+        assert_eq!(eval("x < 0 ? -1 : x > 0 ? 1 : 0"), Ok(Value::Number(0.0)));
+        assert_eq!(
+            eval("(x ? 1 : 2)"),
+            Err(MexeError::TypeMismatch(
+                "a condition cannot be a number".to_owned()
+            ))
+        );
+        assert_eq!(eval("1 / x > 0 ? 1 : 2"), Err(MexeError::DivisionByZero));
+    }
+
+    #[test]
+    fn types_are_checked() {
+        let eval = |expr| eval_ast(&parse(expr).unwrap(), false);
+        let mismatch = |msg: &str| Err(MexeError::TypeMismatch(msg.to_owned()));
+
+        assert_eq!(
+            eval("(1 < 2) + 1"),
+            mismatch("`+` cannot be applied to a boolean")
+        );
+        assert_eq!(
+            eval("-(1 < 2)"),
+            mismatch("`-` cannot be applied to a boolean")
+        );
+        assert_eq!(eval("!1"), mismatch("`!` cannot be applied to a number"));
+        assert_eq!(
+            eval("1 && 1 < 2"),
+            mismatch("`&&` cannot be applied to a number")
+        );
+        assert_eq!(
+            eval("1 > 2 || 1"),
+            mismatch("`||` cannot be applied to a number")
+        );
+        assert_eq!(
+            eval("1 < 2 < 3"),
+            mismatch("`<` cannot be applied to a boolean")
+        );
+        assert_eq!(
+            eval("1 == (1 == 1)"),
+            mismatch("`==` cannot compare a number with a boolean")
+        );
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        let eval = |expr| eval_ast(&parse(expr).unwrap(), false);
+        let mismatch = |msg: &str| Err(MexeError::TypeMismatch(msg.to_owned()));
+
+        assert_eq!(eval("6 & 3"), Ok(Value::Number(2.0)));
+        assert_eq!(eval("6 | 3"), Ok(Value::Number(7.0)));
+        assert_eq!(eval("6 xor 3"), Ok(Value::Number(5.0)));
+        assert_eq!(eval("~5"), Ok(Value::Number(-6.0)));
+        assert_eq!(eval("1 << 2 + 1"), Ok(Value::Number(8.0)));
+        assert_eq!(eval("-16 >> 2"), Ok(Value::Number(-4.0)));
+        assert_eq!(
+            eval("1 | 2 == 2"),
+            mismatch("`|` cannot be applied to a boolean")
+        );
+        assert_eq!(eval("(5 & 1) == 1"), Ok(Value::Bool(true)));
+
+        assert_eq!(
+            eval("1.5 & 1"),
+            mismatch("`&` cannot be applied to 1.5, which is not a 64-bit integer")
+        );
+        assert_eq!(
+            eval("~(2 / 0)"),
+            mismatch("`~` cannot be applied to inf, which is not a 64-bit integer")
+        );
+        assert_eq!(
+            eval("1 << 64"),
+            mismatch("`<<` cannot shift by 64, which is not between 0 and 63")
+        );
+        assert_eq!(
+            eval("~(1 < 2)"),
+            mismatch("`~` cannot be applied to a boolean")
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{Ast, Result};
+
+const INDENT: usize = 4;
+
+/// Formats an expression, breaking it over multiple lines so that it fits in
+/// `width` columns if possible.
+///
+/// Lines are broken before the top-level operators of an operation, such as a
+/// sum, a product or a comparison, with the operator starting the new line.
+/// Conditionals are broken before the `?` and the `:`, and a chain of
+/// conditionals in the `:` branches is kept at the same indentation. Nested
+/// operations that still do not fit are broken in the same way, indented by
+/// four more columns; long parenthesized expressions are opened and closed on
+/// lines of their own. Only the parentheses required by precedence are kept.
+/// A line can still be longer than `width` if a single operand does not fit
+/// in it.
+///
+/// Line breaks are whitespace, so the output can be read back by
+/// [`eval`](crate::eval) and [`parse`](crate::parse).
+///
+/// `T`: type of the expression. Usually a `&str` or a `String`.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let formatted = mexe::format("(1 + 2) * (3.5 - 4 / (5 + 6)) - 7", 22)?;
+///
+/// assert_eq!(formatted, "\
+/// (1 + 2)
+///     * (
+///         3.5
+///         - 4 / (5 + 6)
+///     )
+/// - 7");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// This function will return a [`MexeError`](crate::MexeError) if the input
+/// is not a valid arithmetic expression.
+pub fn format<T>(expression: T, width: usize) -> Result<String>
+where
+    T: AsRef<str>,
+{
+    let ast = crate::parse(expression)?;
+    let mut lines = Vec::new();
+    format_node(&ast, 0, 0, "", width, &mut lines);
+
+    Ok(lines.join("\n"))
+}
+
+// Pushes the lines of `ast`. The first one starts at `indent` with `prefix`
+// (an operator, if the node is the right operand of a broken operation), and
+// the operators of the node itself are broken at `cont`.
+fn format_node(
+    ast: &Ast,
+    indent: usize,
+    cont: usize,
+    prefix: &str,
+    width: usize,
+    lines: &mut Vec<String>,
+) {
+    let line = format!("{:indent$}{}{}", "", prefix, ast, indent = indent);
+
+    if line.len() <= width {
+        lines.push(line);
+        return;
+    }
+
+    match ast {
+        Ast::Neg(inner) if !matches!(inner.as_ref(), Ast::Number(_) | Ast::Var(_)) => {
+            lines.push(format!("{:indent$}{}-(", "", prefix, indent = indent));
+            format_node(inner, indent + INDENT, indent + INDENT, "", width, lines);
+            lines.push(format!("{:indent$})", "", indent = indent));
+        }
+        Ast::Not(inner) if matches!(inner.as_ref(), Ast::Binary(..) | Ast::Cond(..)) => {
+            lines.push(format!("{:indent$}{}!(", "", prefix, indent = indent));
+            format_node(inner, indent + INDENT, indent + INDENT, "", width, lines);
+            lines.push(format!("{:indent$})", "", indent = indent));
+        }
+        Ast::BitNot(inner) if matches!(inner.as_ref(), Ast::Binary(..) | Ast::Cond(..)) => {
+            lines.push(format!("{:indent$}{}~(", "", prefix, indent = indent));
+            format_node(inner, indent + INDENT, indent + INDENT, "", width, lines);
+            lines.push(format!("{:indent$})", "", indent = indent));
+        }
+        Ast::Cond(cond, then, otherwise) => {
+            let parens = cond.precedence() == 0;
+            format_operand(cond, parens, indent, prefix, width, lines);
+            format_node(then, cont, cont + INDENT, "? ", width, lines);
+            format_node(otherwise, cont, cont, ": ", width, lines);
+        }
+        Ast::Binary(..) => {
+            let prec = ast.precedence();
+
+            // flatten the left-associative chain of operators of this level
+            let mut operands = Vec::new();
+            let mut first = ast;
+
+            while let Ast::Binary(o, lhs, rhs) = first {
+                if first.precedence() != prec {
+                    break;
+                }
+
+                operands.push((*o, rhs.as_ref()));
+                first = lhs;
+            }
+
+            operands.reverse();
+
+            format_operand(
+                first,
+                first.precedence() < prec,
+                indent,
+                prefix,
+                width,
+                lines,
+            );
+
+            for (op, operand) in operands {
+                let prefix = format!("{} ", op);
+                let parens = operand.precedence() <= prec;
+                format_operand(operand, parens, cont, &prefix, width, lines);
+            }
+        }
+        _ => lines.push(line),
+    }
+}
+
+fn format_operand(
+    ast: &Ast,
+    parens: bool,
+    indent: usize,
+    prefix: &str,
+    width: usize,
+    lines: &mut Vec<String>,
+) {
+    if !parens {
+        return format_node(ast, indent, indent + INDENT, prefix, width, lines);
+    }
+
+    let line = format!("{:indent$}{}({})", "", prefix, ast, indent = indent);
+
+    if line.len() <= width {
+        lines.push(line);
+    } else {
+        lines.push(format!("{:indent$}{}(", "", prefix, indent = indent));
+        format_node(ast, indent + INDENT, indent + INDENT, "", width, lines);
+        lines.push(format!("{:indent$})", "", indent = indent));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn short_expressions_are_kept_in_one_line() {
+        assert_eq!(format("((1)) +  2*x", 80).unwrap(), "1 + 2 * x");
+        assert_eq!(format("1 + 2 * x", 9).unwrap(), "1 + 2 * x");
+    }
+
+    #[test]
+    fn long_expressions_are_broken() {
+        assert_eq!(format("1 + 2 * x", 8).unwrap(), "1\n+ 2 * x");
+        assert_eq!(format("1 + 2 * x", 1).unwrap(), "1\n+ 2\n    * x");
+        assert_eq!(
+            format("a - -(b + c * d)", 11).unwrap(),
+            "a\n- -(\n    b\n    + c * d\n)"
+        );
+        assert_eq!(format("a * (b - c) / d", 10).unwrap(), "a\n* (b - c)\n/ d");
+        assert_eq!(
+            format("qty > 10 && !(price < 5)", 13).unwrap(),
+            "qty > 10\n&& !(\n    price < 5\n)"
+        );
+        assert_eq!(
+            format("x < 10 ? 5 : x < 100 ? 2.5 + x / 100 : 0", 20).unwrap(),
+            "x < 10\n? 5\n: x < 100\n? 2.5 + x / 100\n: 0"
+        );
+    }
+
+    #[test]
+    fn formatting_preserves_the_expression() {
+        let exprs = [
+            "(6 * ((1.5 / (2 + 3 * 0.1) + 6) * 3)) - 1.1",
+            "((9 + 7.07) * ((((1.5 - 0.001 + 0.005) * (0.7 - 0.33 + (6.00 * 0.05))) / (2.5 + 3 * 0.1) + 6) * 3)) - 1.1 * (1.00 * 2838.88736 * 3 / (6 - 2))",
+            "-(x - (y - -(z / (w / v))))",
+            "!(a < b + 1 && (c == d || !e)) || f >= 2 * g != (h <= 0)",
+            "(a ? b : c) ? d + (e ? f : g) : !(h ? i : j) ? k : l",
+            "~(mask & (flags | 1 << bit)) xor (value >> 2) & ~(1 << 8)",
+        ];
+
+        for expr in exprs.iter() {
+            for width in [0, 10, 20, 40].iter() {
+                let formatted = format(expr, *width).unwrap();
+                assert_eq!(
+                    parse(&formatted).unwrap(),
+                    parse(expr).unwrap(),
+                    "{}",
+                    formatted
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{Ast, Operator};
+
+/// Renders an expression as LaTeX math.
+///
+/// Divisions become `\frac{}{}`, multiplications use `\cdot`, and
+/// `\left( \right)` are only inserted where the expression would otherwise be
+/// read differently, such as around the right operand of a subtraction or a
+/// negative factor. Variables with more than one character are set in upright
+/// type, except for names like `x_1`, which are rendered with a subscript,
+/// and `pi`, which is rendered as `\pi`.
+/// Conditionals are rendered with the `cases` environment.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let ast = mexe::parse("(x + 1) / 2 * -y - (a - b)")?;
+/// assert_eq!(
+///     mexe::to_latex(&ast),
+///     r"\frac{x + 1}{2} \cdot \left(-y\right) - \left(a - b\right)"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_latex(ast: &Ast) -> String {
+    let mut latex = String::new();
+    write_latex(ast, &mut latex);
+    latex
+}
+
+fn write_latex(ast: &Ast, out: &mut String) {
+    match ast {
+        Ast::Number(n) => out.push_str(&n.to_string()),
+        Ast::Var(name) => write_var(name, out),
+        Ast::Neg(inner) => {
+            out.push('-');
+            write_operand(inner, inner.is_sum() || inner.is_negative(), out);
+        }
+        Ast::Not(inner) => {
+            out.push_str("\\lnot ");
+            write_operand(inner, matches!(inner.as_ref(), Ast::Binary(..)), out);
+        }
+        Ast::BitNot(inner) => {
+            out.push_str("\\sim ");
+            write_operand(inner, matches!(inner.as_ref(), Ast::Binary(..)), out);
+        }
+        Ast::Cond(cond, then, otherwise) => {
+            out.push_str("\\begin{cases} ");
+            write_latex(then, out);
+            out.push_str(" & \\text{if } ");
+            write_latex(cond, out);
+            out.push_str(" \\\\ ");
+            write_latex(otherwise, out);
+            out.push_str(" & \\text{otherwise} \\end{cases}");
+        }
+        Ast::Binary(Operator::Div, num, den) => {
+            out.push_str("\\frac{");
+            write_latex(num, out);
+            out.push_str("}{");
+            write_latex(den, out);
+            out.push('}');
+        }
+        Ast::Binary(op, lhs, rhs) => {
+            let (lhs_parens, rhs_parens) = needs_parens(*op, lhs, rhs);
+
+            write_operand(lhs, lhs_parens, out);
+            out.push_str(match op {
+                Operator::Add => " + ",
+                Operator::Sub => " - ",
+                Operator::Mul | Operator::Div => " \\cdot ",
+                Operator::Lt => " < ",
+                Operator::Le => " \\leq ",
+                Operator::Gt => " > ",
+                Operator::Ge => " \\geq ",
+                Operator::Eq => " = ",
+                Operator::Ne => " \\neq ",
+                Operator::And => " \\land ",
+                Operator::Or => " \\lor ",
+                Operator::BitAnd => " \\mathbin{\\&} ",
+                Operator::BitOr => " \\mathbin{|} ",
+                Operator::BitXor => " \\oplus ",
+                Operator::Shl => " \\ll ",
+                Operator::Shr => " \\gg ",
+            });
+            write_operand(rhs, rhs_parens, out);
+        }
+    }
+}
+
+// Whether the operands of a binary operation, other than a division, need
+// parentheses. Divisions are rendered as fractions, and products with a dot,
+// so fewer are needed than when printing an expression.
+pub(crate) fn needs_parens(op: Operator, lhs: &Ast, rhs: &Ast) -> (bool, bool) {
+    let prec = op.precedence();
+
+    match op {
+        Operator::Add => (
+            lhs.precedence() < prec,
+            rhs.is_negative() || rhs.precedence() < prec,
+        ),
+        Operator::Sub => (lhs.precedence() < prec, rhs.is_sum() || rhs.is_negative()),
+        Operator::Mul => (lhs.is_sum(), rhs.is_sum() || rhs.is_negative()),
+        _ => (lhs.precedence() < prec, rhs.precedence() <= prec),
+    }
+}
+
+fn write_operand(ast: &Ast, parens: bool, out: &mut String) {
+    if parens {
+        out.push_str("\\left(");
+        write_latex(ast, out);
+        out.push_str("\\right)");
+    } else {
+        write_latex(ast, out);
+    }
+}
+
+fn write_var(name: &str, out: &mut String) {
+    match name.split_once('_') {
+        _ if name == "pi" => out.push_str("\\pi"),
+        _ if name.len() == 1 => out.push_str(name),
+        Some((base, sub)) if base.len() == 1 && !sub.is_empty() && !sub.contains('_') => {
+            out.push_str(base);
+            out.push_str("_{");
+            out.push_str(sub);
+            out.push('}');
+        }
+        _ => {
+            out.push_str("\\mathrm{");
+            out.push_str(&name.replace('_', "\\_"));
+            out.push('}');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn latex(expr: &str) -> String {
+        to_latex(&parse(expr).unwrap())
+    }
+
+    #[test]
+    fn test_to_latex() {
+        assert_eq!(latex("1.5"), "1.5");
+        assert_eq!(latex("-x"), "-x");
+        assert_eq!(latex("-(-1)"), r"-\left(-1\right)");
+        assert_eq!(latex("-(a + b)"), r"-\left(a + b\right)");
+        assert_eq!(latex("-(a * b)"), r"-a \cdot b");
+        assert_eq!(latex("a + (b + c)"), "a + b + c");
+        assert_eq!(latex("a - (b + c)"), r"a - \left(b + c\right)");
+        assert_eq!(latex("a + -b * c"), r"a + \left(-b \cdot c\right)");
+        assert_eq!(
+            latex("(a - b) * (c + d)"),
+            r"\left(a - b\right) \cdot \left(c + d\right)"
+        );
+        assert_eq!(latex("a * (b * c)"), r"a \cdot b \cdot c");
+        assert_eq!(latex("(a + b) / (c / d)"), r"\frac{a + b}{\frac{c}{d}}");
+        assert_eq!(latex("-(a / b)"), r"-\frac{a}{b}");
+        assert_eq!(latex("2 * pi * r"), r"2 \cdot \pi \cdot r");
+    }
+
+    #[test]
+    fn comparisons_and_logical_operators() {
+        assert_eq!(latex("a <= b + 1"), r"a \leq b + 1");
+        assert_eq!(latex("a != b && !c"), r"a \neq b \land \lnot c");
+        assert_eq!(
+            latex("!(a || b) || (a == b) + 1"),
+            r"\lnot \left(a \lor b\right) \lor \left(a = b\right) + 1"
+        );
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        assert_eq!(
+            latex("a & b | c xor (d << 1)"),
+            r"a \mathbin{\&} b \mathbin{|} c \oplus d \ll 1"
+        );
+        assert_eq!(
+            latex("~(a >> 2) + ~b"),
+            r"\sim \left(a \gg 2\right) + \sim b"
+        );
+    }
+
+    #[test]
+    fn conditionals_are_cases() {
+        assert_eq!(
+            latex("x < 0 ? -x : x"),
+            r"\begin{cases} -x & \text{if } x < 0 \\ x & \text{otherwise} \end{cases}"
+        );
+    }
+
+    #[test]
+    fn variables_are_escaped() {
+        assert_eq!(latex("x_1"), "x_{1}");
+        assert_eq!(latex("x_max"), "x_{max}");
+        assert_eq!(latex("rate"), r"\mathrm{rate}");
+        assert_eq!(latex("unit_cost"), r"\mathrm{unit\_cost}");
+        assert_eq!(latex("x_"), r"\mathrm{x\_}");
+    }
+}
@@ -1,57 +1,338 @@
-use crate::{MexeError, Operator, Result, Token};
+use std::borrow::Cow;
+
+use crate::parser::ParseError;
+use crate::{MexeError, NumberFormat, Operator, Result, Syntax, Token};
 
 enum LexerState {
     Normal,
     ReadingNumber(usize),
     ReadingDecimals(usize),
+    ReadingIdentifier(usize),
+    // a number with a `0x`, `0o` or `0b` prefix, and its base
+    ReadingRadix(usize, u32),
+}
+
+pub(crate) fn get_tokens(expression: &str) -> Result<Vec<Token<'_>>> {
+    tokenize(expression, &Syntax::default(), false, None)
+}
+
+pub(crate) fn get_tokens_with<'a>(expression: &'a str, syntax: &Syntax) -> Result<Vec<Token<'a>>> {
+    tokenize(expression, syntax, false, None)
+}
+
+// Same as `get_tokens_with`, but newlines are tokens, as they separate
+// statements.
+pub(crate) fn get_program_tokens<'a>(program: &'a str, syntax: &Syntax) -> Result<Vec<Token<'a>>> {
+    tokenize(program, syntax, true, None)
+}
+
+// Turns an error of the parser into a `MexeError`. The token is shown as it
+// is written with `syntax`, and a variable used before being assigned is
+// reported with the index of the character where it starts.
+pub(crate) fn parse_error(
+    expression: &str,
+    syntax: &Syntax,
+    newlines: bool,
+    tokens: &[Token],
+    err: ParseError,
+) -> MexeError {
+    match err {
+        // with a decimal comma, arguments are separated by `;`
+        ParseError::UnexpectedToken(index)
+            if tokens[index] == Token::Comma
+                && syntax.number_format == NumberFormat::DecimalComma =>
+        {
+            MexeError::UnexpectedToken(
+                ";".to_owned(),
+                position(expression, syntax, newlines, index),
+            )
+        }
+        ParseError::UnexpectedToken(index) => MexeError::UnexpectedToken(
+            tokens[index].to_string(),
+            position(expression, syntax, newlines, index),
+        ),
+        ParseError::UseBeforeDefinition(index) => MexeError::UseBeforeDefinition(
+            tokens[index].to_string(),
+            position(expression, syntax, newlines, index),
+        ),
+    }
 }
 
-pub(crate) fn get_tokens(expression: &str) -> Result<Vec<Token>> {
+// Index of the character of the expression where its token `index` starts.
+// The positions are only needed to report errors, so they are not recorded by
+// `get_tokens`.
+pub(crate) fn position(expression: &str, syntax: &Syntax, newlines: bool, index: usize) -> usize {
+    let mut positions = Vec::new();
+    let _ = tokenize(expression, syntax, newlines, Some(&mut positions));
+    let byte = positions.get(index).copied().unwrap_or(expression.len());
+
+    expression[..byte].chars().count()
+}
+
+// Turns the byte index of an error into the index of the character, as the
+// lexer works with bytes but errors point to characters.
+fn char_index(expression: &str, err: MexeError) -> MexeError {
+    let index = |i: usize| expression[..i].chars().count();
+
+    match err {
+        MexeError::InvalidCharacter(i) => MexeError::InvalidCharacter(index(i)),
+        MexeError::UnexpectedCharacter(c, i) => MexeError::UnexpectedCharacter(c, index(i)),
+        MexeError::InvalidDigit(c, i) => MexeError::InvalidDigit(c, index(i)),
+        MexeError::MisplacedSeparator(i) => MexeError::MisplacedSeparator(index(i)),
+        MexeError::UnterminatedComment(i) => MexeError::UnterminatedComment(index(i)),
+        MexeError::LiteralTooLarge(i) => MexeError::LiteralTooLarge(index(i)),
+        err => err,
+    }
+}
+
+fn tokenize<'a>(
+    expression: &'a str,
+    syntax: &Syntax,
+    newlines: bool,
+    positions: Option<&mut Vec<usize>>,
+) -> Result<Vec<Token<'a>>> {
+    tokenize_bytes(expression, syntax, newlines, positions)
+        .map_err(|err| char_index(expression, err))
+}
+
+fn tokenize_bytes<'a>(
+    expression: &'a str,
+    syntax: &Syntax,
+    newlines: bool,
+    mut positions: Option<&mut Vec<usize>>,
+) -> Result<Vec<Token<'a>>> {
     let chars = expression.as_bytes();
     let mut tokens = Vec::with_capacity(chars.len() / 2 + 2); // heuristic
     let mut state = LexerState::Normal;
+    let decimal_comma = syntax.number_format == NumberFormat::DecimalComma;
+    let decimal = if decimal_comma { b',' } else { b'.' };
+    // depth of parentheses, where `;` separates arguments with a decimal comma
+    let mut depth = 0usize;
+
+    let mut i = 0;
+
+    while i < chars.len() {
+        // bytes left of a token of more than one byte, such as a two-character
+        // operator, or of a character of more than one byte
+        let mut skip = 0;
 
-    for i in 0..chars.len() {
-        let (in_number, token) = match chars[i] {
-            b' ' => (false, None),
-            b'(' => (false, Some(Token::LPar)),
-            b')' => (false, Some(Token::RPar)),
+        let (in_literal, token) = match chars[i] {
+            // a newline inside parentheses does not end a statement
+            b'\n' if newlines && depth == 0 => (false, Some(Token::Newline)),
+            b' ' | b'\t' | b'\n' | b'\x0B' | b'\x0C' | b'\r' => (false, None),
+            b'(' => {
+                depth += 1;
+                (false, Some(Token::LPar))
+            }
+            b')' => {
+                depth = depth.saturating_sub(1);
+                (false, Some(Token::RPar))
+            }
             b'*' => (false, Some(Token::Op(Operator::Mul))),
             b'+' => (false, Some(Token::Op(Operator::Add))),
             b'-' => (false, Some(Token::Op(Operator::Sub))),
+            // comments: `#` until the end of the line, which is still a
+            // token in programs, and `/*` until `*/`
+            b'#' => {
+                let len = chars[i..].iter().position(|&c| c == b'\n');
+                skip = len.unwrap_or(chars.len() - i) - 1;
+                (false, None)
+            }
+            b'/' if chars.get(i + 1) == Some(&b'*') => {
+                let len = chars[i + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .ok_or(MexeError::UnterminatedComment(i))?;
+                skip = len + 3;
+                (false, None)
+            }
             b'/' => (false, Some(Token::Op(Operator::Div))),
+            b'~' => (false, Some(Token::BitNot)),
+            b'?' => (false, Some(Token::Question)),
+            b':' => (false, Some(Token::Colon)),
+            b',' if !decimal_comma => (false, Some(Token::Comma)),
+            b';' if decimal_comma && depth > 0 => (false, Some(Token::Comma)),
+            b';' => (false, Some(Token::Semicolon)),
+
+            b'=' | b'<' | b'>' | b'!' | b'&' | b'|' => {
+                let (token, len) = match (chars[i], chars.get(i + 1)) {
+                    (b'=', Some(b'=')) => (Token::Op(Operator::Eq), 2),
+                    (b'<', Some(b'=')) => (Token::Op(Operator::Le), 2),
+                    (b'>', Some(b'=')) => (Token::Op(Operator::Ge), 2),
+                    (b'!', Some(b'=')) => (Token::Op(Operator::Ne), 2),
+                    (b'&', Some(b'&')) => (Token::Op(Operator::And), 2),
+                    (b'|', Some(b'|')) => (Token::Op(Operator::Or), 2),
+                    (b'<', Some(b'<')) => (Token::Op(Operator::Shl), 2),
+                    (b'>', Some(b'>')) => (Token::Op(Operator::Shr), 2),
+                    (b'=', _) => (Token::Assign, 1),
+                    (b'<', _) => (Token::Op(Operator::Lt), 1),
+                    (b'>', _) => (Token::Op(Operator::Gt), 1),
+                    (b'!', _) => (Token::Not, 1),
+                    (b'&', _) => (Token::Op(Operator::BitAnd), 1),
+                    _ => (Token::Op(Operator::BitOr), 1),
+                };
+
+                skip = len - 1;
+                (false, Some(token))
+            }
+
+            // a literal with only digits and a decimal point is read at once,
+            // the others digit by digit
+            b'0'..=b'9' if matches!(state, LexerState::Normal) => {
+                match plain_number(&chars[i..], decimal_comma) {
+                    Some(len) => {
+                        let number = parse_number(&expression[i..i + len], i)?;
+                        push(&mut tokens, &mut positions, Token::Number(number), i);
+                        skip = len - 1;
+                        (false, None)
+                    }
+                    None => {
+                        state = LexerState::ReadingNumber(i);
+                        (true, None)
+                    }
+                }
+            }
 
-            b'0'..=b'9' => {
+            c @ b'0'..=b'9' => {
                 state = match state {
                     LexerState::Normal => LexerState::ReadingNumber(i),
-                    LexerState::ReadingNumber(_) | LexerState::ReadingDecimals(_) => state,
+                    LexerState::ReadingRadix(_, radix) if !is_digit(c, radix) => {
+                        return Err(MexeError::InvalidDigit(c, i))
+                    }
+                    LexerState::ReadingNumber(_)
+                    | LexerState::ReadingDecimals(_)
+                    | LexerState::ReadingIdentifier(_)
+                    | LexerState::ReadingRadix(..) => state,
                 };
 
                 (true, None)
             }
 
-            b'.' => {
+            // with a decimal comma, `.` separates groups of three digits, as
+            // in `1.234,5`, after a first group of one to three digits
+            b'.' if decimal_comma && matches!(state, LexerState::ReadingNumber(_)) => {
+                let start = match state {
+                    LexerState::ReadingNumber(n) => n,
+                    _ => unreachable!(),
+                };
+                let first = !chars[start..i].contains(&b'.');
+                let group = chars.get(i + 1..i + 4);
+                let after = chars.get(i + 4).copied();
+
+                if (first && i - start > 3)
+                    || !matches!(group, Some(group) if group.iter().all(u8::is_ascii_digit))
+                    || matches!(after, Some(c) if c.is_ascii_digit() || is_separator(c))
+                {
+                    return Err(MexeError::MisplacedSeparator(i));
+                }
+
+                (true, None)
+            }
+
+            // the decimal separator must be between digits, unless the
+            // syntax is lenient, as in `.5` and `5.`
+            c @ (b'.' | b',') => {
+                let digit_next = matches!(chars.get(i + 1), Some(c) if c.is_ascii_digit());
+
                 state = match state {
-                    LexerState::Normal | LexerState::ReadingDecimals(_) => {
-                        return Err(MexeError::UnexpectedCharacter(b'.', i))
+                    LexerState::ReadingNumber(n)
+                        if c == decimal && (digit_next || syntax.lenient_literals) =>
+                    {
+                        LexerState::ReadingDecimals(n)
+                    }
+                    LexerState::Normal if c == decimal && digit_next && syntax.lenient_literals => {
+                        LexerState::ReadingDecimals(i)
+                    }
+                    _ => return Err(MexeError::UnexpectedCharacter(c, i)),
+                };
+
+                (true, None)
+            }
+
+            // digit separators, as in `1_000`; elsewhere, `_` is part of an
+            // identifier
+            c @ (b'_' | b'\'')
+                if (c == b'_' || syntax.apostrophes)
+                    && matches!(
+                        state,
+                        LexerState::ReadingNumber(_)
+                            | LexerState::ReadingDecimals(_)
+                            | LexerState::ReadingRadix(..)
+                    ) =>
+            {
+                let radix = match state {
+                    LexerState::ReadingRadix(_, radix) => radix,
+                    _ => 10,
+                };
+
+                // a separator must be between two digits of the number
+                let next = chars.get(i + 1).copied();
+
+                if !is_digit(chars[i - 1], radix) {
+                    return Err(MexeError::MisplacedSeparator(i));
+                } else if next == Some(b'_') || (syntax.apostrophes && next == Some(b'\'')) {
+                    return Err(MexeError::MisplacedSeparator(i + 1));
+                } else if !matches!(next, Some(next) if is_digit(next, radix)) {
+                    return Err(MexeError::MisplacedSeparator(i));
+                }
+
+                (true, None)
+            }
+
+            c @ (b'a'..=b'z' | b'A'..=b'Z' | b'_') => {
+                state = match state {
+                    LexerState::Normal => LexerState::ReadingIdentifier(i),
+                    LexerState::ReadingIdentifier(_) => state,
+                    LexerState::ReadingNumber(n) if i == n + 1 && chars[n] == b'0' => {
+                        match c.to_ascii_lowercase() {
+                            b'x' => LexerState::ReadingRadix(n, 16),
+                            b'o' => LexerState::ReadingRadix(n, 8),
+                            b'b' => LexerState::ReadingRadix(n, 2),
+                            _ => return Err(MexeError::UnexpectedCharacter(c, i)),
+                        }
+                    }
+                    LexerState::ReadingRadix(_, radix) if is_digit(c, radix) => state,
+                    LexerState::ReadingRadix(..) => return Err(MexeError::InvalidDigit(c, i)),
+                    LexerState::ReadingNumber(_) | LexerState::ReadingDecimals(_) => {
+                        return Err(MexeError::UnexpectedCharacter(c, i))
                     }
-                    LexerState::ReadingNumber(n) => LexerState::ReadingDecimals(n),
                 };
 
                 (true, None)
             }
+            // non-ASCII characters: symbols copied from documents, and
+            // whitespace if enabled. `expression[i..]` starts at a character
+            // boundary, as the other bytes of the previous characters were
+            // skipped
+            0x80.. => {
+                let c = expression[i..].chars().next().unwrap(); // infallible
+                let token = match c {
+                    '×' | '·' => Some(Token::Op(Operator::Mul)),
+                    '÷' => Some(Token::Op(Operator::Div)),
+                    '−' => Some(Token::Op(Operator::Sub)),
+                    'π' => Some(Token::Ident("pi")),
+                    c if c.is_whitespace() && syntax.unicode_whitespace => None,
+                    _ => return Err(MexeError::InvalidCharacter(i)),
+                };
+
+                skip = c.len_utf8() - 1;
+                (false, token)
+            }
             _ => return Err(MexeError::InvalidCharacter(i)),
         };
 
-        if !in_number {
+        if !in_literal {
             match state {
                 LexerState::ReadingNumber(n) | LexerState::ReadingDecimals(n) => {
-                    let number = std::str::from_utf8(&chars[n..i]).unwrap(); // infallible
-                    let number = number
-                        .parse::<f64>()
-                        .unwrap_or_else(|_| panic!("input:|{}|", number)); // infallible
-
-                    tokens.push(Token::Number(number));
+                    let number = decimal_number(chars, n, i, decimal_comma)?;
+                    push(&mut tokens, &mut positions, Token::Number(number), n);
+                }
+                LexerState::ReadingRadix(n, radix) => {
+                    let number = radix_number(chars, n, i, radix)?;
+                    push(&mut tokens, &mut positions, Token::Number(number), n);
+                }
+                LexerState::ReadingIdentifier(n) => {
+                    push(&mut tokens, &mut positions, ident(&expression[n..i]), n);
                 }
                 _ => (),
             }
@@ -60,26 +341,163 @@ pub(crate) fn get_tokens(expression: &str) -> Result<Vec<Token>> {
         }
 
         if let Some(token) = token {
-            tokens.push(token);
+            push(&mut tokens, &mut positions, token, i);
         }
+
+        i += 1 + skip;
     }
 
     match state {
         LexerState::ReadingNumber(n) | LexerState::ReadingDecimals(n) => {
-            let number = std::str::from_utf8(&chars[n..]).unwrap(); // infallible
-            let number = number
-                .parse::<f64>()
-                .unwrap_or_else(|_| panic!("input:|{}|", number)); // infallible
-
-            tokens.push(Token::Number(number));
+            let number = decimal_number(chars, n, chars.len(), decimal_comma)?;
+            push(&mut tokens, &mut positions, Token::Number(number), n);
+        }
+        LexerState::ReadingRadix(n, radix) => {
+            let number = radix_number(chars, n, chars.len(), radix)?;
+            push(&mut tokens, &mut positions, Token::Number(number), n);
+        }
+        LexerState::ReadingIdentifier(n) => {
+            push(&mut tokens, &mut positions, ident(&expression[n..]), n);
         }
         _ => (),
     }
 
-    tokens.push(Token::EOI);
+    push(&mut tokens, &mut positions, Token::EOI, chars.len());
     Ok(tokens)
 }
 
+fn is_digit(c: u8, radix: u32) -> bool {
+    (c as char).is_digit(radix)
+}
+
+fn is_separator(c: u8) -> bool {
+    c == b'_' || c == b'\''
+}
+
+// Value of the decimal literal in `chars[start..end]`, which was already
+// checked, without its digit separators. Only a literal that has them, or a
+// decimal comma, is copied to be rewritten. A value too large to be finite is
+// an error at the start of the literal, as infinity is not a number that can be
+// written back.
+fn decimal_number(chars: &[u8], start: usize, end: usize, decimal_comma: bool) -> Result<f64> {
+    let literal = &chars[start..end];
+    let rewrite = |c: u8| is_separator(c) || c == b',' || decimal_comma && c == b'.';
+
+    let number: Cow<str> = match literal.iter().any(|&c| rewrite(c)) {
+        false => Cow::Borrowed(std::str::from_utf8(literal).unwrap()), // infallible
+        true => literal
+            .iter()
+            .filter(|&&c| !(is_separator(c) || decimal_comma && c == b'.'))
+            .map(|&c| if c == b',' { '.' } else { c as char })
+            .collect(),
+    };
+
+    parse_number(&number, start)
+}
+
+// Length of the decimal literal that `chars` starts with, if it only has
+// digits and a decimal point between digits, and is not followed by a
+// character that would make it longer or an error, such as a letter.
+fn plain_number(chars: &[u8], decimal_comma: bool) -> Option<usize> {
+    let digits = |start: usize| {
+        chars[start..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
+    let mut len = digits(0);
+
+    if !decimal_comma && chars.get(len) == Some(&b'.') {
+        match digits(len + 1) {
+            0 => return None,
+            decimals => len += 1 + decimals,
+        }
+    }
+
+    match chars.get(len) {
+        Some(c) if c.is_ascii_alphanumeric() || matches!(c, b'.' | b',' | b'_' | b'\'') => None,
+        _ => Some(len),
+    }
+}
+
+// Value of a decimal literal that starts at byte `start` and has only digits
+// and a decimal point. With at most 15 digits, the digits and the power of ten
+// to divide them by are exact as `f64`, so the division rounds correctly and
+// is much faster than parsing.
+fn parse_number(literal: &str, start: usize) -> Result<f64> {
+    const POWERS: [f64; 16] = [
+        1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15,
+    ];
+
+    if literal.len() <= 15 {
+        let point = literal.find('.');
+        let digits = literal
+            .bytes()
+            .filter(|&c| c != b'.')
+            .fold(0i64, |n, c| n * 10 + (c - b'0') as i64);
+
+        return Ok(match point {
+            Some(point) => digits as f64 / POWERS[literal.len() - point - 1],
+            None => digits as f64,
+        });
+    }
+
+    let number = literal
+        .parse::<f64>()
+        .unwrap_or_else(|_| panic!("input:|{}|", literal)); // infallible
+
+    match number.is_finite() {
+        true => Ok(number),
+        false => Err(MexeError::LiteralTooLarge(start)),
+    }
+}
+
+// Value of the literal in `chars[start..end]`, which starts with a two
+// character prefix followed by digits that were already checked. A prefix
+// without digits is an error at the letter of the prefix, and a value that an
+// `f64` cannot hold exactly is an error at the start of the literal.
+fn radix_number(chars: &[u8], start: usize, end: usize, radix: u32) -> Result<f64> {
+    const MAX_EXACT: u64 = 1 << f64::MANTISSA_DIGITS;
+
+    if end == start + 2 {
+        return Err(MexeError::UnexpectedCharacter(chars[start + 1], start + 1));
+    }
+
+    chars[start + 2..end]
+        .iter()
+        .filter(|&&c| !is_separator(c))
+        .try_fold(0u64, |val, &c| {
+            let digit = (c as char).to_digit(radix).unwrap() as u64; // infallible
+            val.checked_mul(radix as u64)
+                .and_then(|val| val.checked_add(digit))
+                .filter(|&val| val <= MAX_EXACT)
+        })
+        .map(|val| val as f64)
+        .ok_or(MexeError::LiteralTooLarge(start))
+}
+
+// `xor` is an operator, not a variable.
+fn ident(name: &str) -> Token<'_> {
+    match name {
+        "xor" => Token::Op(Operator::BitXor),
+        name => Token::Ident(name),
+    }
+}
+
+#[inline]
+fn push<'a>(
+    tokens: &mut Vec<Token<'a>>,
+    positions: &mut Option<&mut Vec<usize>>,
+    token: Token<'a>,
+    position: usize,
+) {
+    tokens.push(token);
+
+    if let Some(positions) = positions {
+        positions.push(position);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,6 +510,7 @@ mod tests {
             "1.1+1.2",
             "183.387+(2*2.3)",
             "(2.3 + 1) - ((2.55 - 91381.832) / (83767.3 * 22))",
+            "x * (y_1 + 2)",
         ];
 
         for expr in exprs.iter() {
@@ -108,10 +527,511 @@ mod tests {
             "1.1+1.",
             "183.+(2*2.3)",
             "(2.3 ++ 1)",
+            "2x",
+            "x.1",
         ];
 
         for expr in exprs.iter() {
             let _tokens = get_tokens(expr);
         }
     }
+
+    #[test]
+    fn identifiers_are_tokenized() {
+        assert_eq!(
+            get_tokens("x1+_y").unwrap(),
+            vec![
+                Token::Ident("x1"),
+                Token::Op(Operator::Add),
+                Token::Ident("_y"),
+                Token::EOI
+            ]
+        );
+        assert_eq!(
+            get_tokens("2x"),
+            Err(MexeError::UnexpectedCharacter(b'x', 1))
+        );
+    }
+
+    #[test]
+    fn operators_of_two_characters_are_tokenized() {
+        assert_eq!(
+            get_tokens("a<=b<c!=!d==e>f>=g&&h||i").unwrap(),
+            vec![
+                Token::Ident("a"),
+                Token::Op(Operator::Le),
+                Token::Ident("b"),
+                Token::Op(Operator::Lt),
+                Token::Ident("c"),
+                Token::Op(Operator::Ne),
+                Token::Not,
+                Token::Ident("d"),
+                Token::Op(Operator::Eq),
+                Token::Ident("e"),
+                Token::Op(Operator::Gt),
+                Token::Ident("f"),
+                Token::Op(Operator::Ge),
+                Token::Ident("g"),
+                Token::Op(Operator::And),
+                Token::Ident("h"),
+                Token::Op(Operator::Or),
+                Token::Ident("i"),
+                Token::EOI
+            ]
+        );
+        assert_eq!(
+            get_tokens("x = = 1").unwrap()[1..3],
+            [Token::Assign, Token::Assign]
+        );
+    }
+
+    #[test]
+    fn bitwise_operators_are_tokenized() {
+        assert_eq!(
+            get_tokens("a&&&b<<<c>>d xor xor_e|~f").unwrap(),
+            vec![
+                Token::Ident("a"),
+                Token::Op(Operator::And),
+                Token::Op(Operator::BitAnd),
+                Token::Ident("b"),
+                Token::Op(Operator::Shl),
+                Token::Op(Operator::Lt),
+                Token::Ident("c"),
+                Token::Op(Operator::Shr),
+                Token::Ident("d"),
+                Token::Op(Operator::BitXor),
+                Token::Ident("xor_e"),
+                Token::Op(Operator::BitOr),
+                Token::BitNot,
+                Token::Ident("f"),
+                Token::EOI
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_in_other_bases_are_tokenized() {
+        assert_eq!(
+            get_tokens("0x1F+0o17*0b1011-0XaB+0B1+0O0+0x0").unwrap(),
+            vec![
+                Token::Number(31.0),
+                Token::Op(Operator::Add),
+                Token::Number(15.0),
+                Token::Op(Operator::Mul),
+                Token::Number(11.0),
+                Token::Op(Operator::Sub),
+                Token::Number(171.0),
+                Token::Op(Operator::Add),
+                Token::Number(1.0),
+                Token::Op(Operator::Add),
+                Token::Number(0.0),
+                Token::Op(Operator::Add),
+                Token::Number(0.0),
+                Token::EOI
+            ]
+        );
+        assert_eq!(
+            get_tokens("0x20_0000_0000_0000").unwrap(),
+            vec![Token::Number(9007199254740992.0), Token::EOI]
+        );
+    }
+
+    #[test]
+    fn decimal_literals_are_rounded_like_parse() {
+        let literals = [
+            "0.1",
+            "0.3",
+            "2.675",
+            "123456.789",
+            "0.000000000001",
+            "999999999999999",
+            "9007199254740993",
+            "0.1234567890123",
+            "3.14159265358979",
+            "1.00000000000000001",
+        ];
+
+        for literal in literals.iter() {
+            assert_eq!(
+                get_tokens(literal).unwrap(),
+                vec![Token::Number(literal.parse().unwrap()), Token::EOI],
+                "{}",
+                literal
+            );
+        }
+    }
+
+    #[test]
+    fn inexact_literals_are_rejected() {
+        assert_eq!(
+            get_tokens("0x20000000000001"),
+            Err(MexeError::LiteralTooLarge(0))
+        );
+        assert_eq!(
+            get_tokens("1 + 0xffffffffffffffff"),
+            Err(MexeError::LiteralTooLarge(4))
+        );
+        assert_eq!(
+            get_tokens("π + 0xFFFFFFFFFFFFFFFFF"),
+            Err(MexeError::LiteralTooLarge(4))
+        );
+        assert_eq!(
+            get_tokens(&format!("0b1{}", "0".repeat(64))),
+            Err(MexeError::LiteralTooLarge(0))
+        );
+        assert_eq!(
+            get_tokens(&format!("2 * 1{}.5", "0".repeat(309))),
+            Err(MexeError::LiteralTooLarge(4))
+        );
+        assert_eq!(
+            get_tokens(&format!("1{}", "0".repeat(308))).unwrap(),
+            vec![Token::Number(1e308), Token::EOI]
+        );
+    }
+
+    #[test]
+    fn bad_digits_are_reported() {
+        assert_eq!(get_tokens("0b102"), Err(MexeError::InvalidDigit(b'2', 4)));
+        assert_eq!(get_tokens("1+0o78"), Err(MexeError::InvalidDigit(b'8', 5)));
+        assert_eq!(get_tokens("0x1g"), Err(MexeError::InvalidDigit(b'g', 3)));
+        assert_eq!(
+            get_tokens("0x1.5"),
+            Err(MexeError::UnexpectedCharacter(b'.', 3))
+        );
+        assert_eq!(
+            get_tokens("0x + 1"),
+            Err(MexeError::UnexpectedCharacter(b'x', 1))
+        );
+        assert_eq!(
+            get_tokens("1 + 0b"),
+            Err(MexeError::UnexpectedCharacter(b'b', 5))
+        );
+        assert_eq!(
+            get_tokens("1x1"),
+            Err(MexeError::UnexpectedCharacter(b'x', 1))
+        );
+        assert_eq!(
+            get_tokens("00x1"),
+            Err(MexeError::UnexpectedCharacter(b'x', 2))
+        );
+    }
+
+    #[test]
+    fn digit_separators_are_skipped() {
+        let number = |expr| get_tokens(expr).map(|tokens| tokens[0]);
+        let apostrophes = Syntax::new().apostrophes(true);
+
+        assert_eq!(number("1_000_000"), Ok(Token::Number(1000000.0)));
+        assert_eq!(number("1_2.3_4"), Ok(Token::Number(12.34)));
+        assert_eq!(number("0xFF_FF"), Ok(Token::Number(65535.0)));
+        assert_eq!(number("0b1010_0101"), Ok(Token::Number(165.0)));
+        assert_eq!(
+            get_tokens_with("1'000'000+2_000", &apostrophes),
+            Ok(vec![
+                Token::Number(1000000.0),
+                Token::Op(Operator::Add),
+                Token::Number(2000.0),
+                Token::EOI
+            ])
+        );
+        assert_eq!(
+            get_tokens("_1+x_1"),
+            Ok(vec![
+                Token::Ident("_1"),
+                Token::Op(Operator::Add),
+                Token::Ident("x_1"),
+                Token::EOI
+            ])
+        );
+    }
+
+    #[test]
+    fn misplaced_separators_are_reported() {
+        let apostrophes = Syntax::new().apostrophes(true);
+
+        assert_eq!(get_tokens("1__000"), Err(MexeError::MisplacedSeparator(2)));
+        assert_eq!(get_tokens("2 * 1_"), Err(MexeError::MisplacedSeparator(5)));
+        assert_eq!(get_tokens("1_.5"), Err(MexeError::MisplacedSeparator(1)));
+        assert_eq!(
+            get_tokens_with("1._5", &Syntax::new().lenient_literals(true)),
+            Err(MexeError::MisplacedSeparator(2))
+        );
+        assert_eq!(get_tokens("0x_1"), Err(MexeError::MisplacedSeparator(2)));
+        assert_eq!(get_tokens("1_x"), Err(MexeError::MisplacedSeparator(1)));
+        assert_eq!(
+            get_tokens_with("1'_000", &apostrophes),
+            Err(MexeError::MisplacedSeparator(2))
+        );
+        assert_eq!(
+            get_tokens_with("1000' + 1", &apostrophes),
+            Err(MexeError::MisplacedSeparator(4))
+        );
+        assert_eq!(get_tokens("1'000"), Err(MexeError::InvalidCharacter(1)));
+        assert_eq!(
+            get_tokens_with("x'1", &apostrophes),
+            Err(MexeError::InvalidCharacter(1))
+        );
+    }
+
+    #[test]
+    fn decimal_comma() {
+        let syntax = Syntax::new().number_format(NumberFormat::DecimalComma);
+        let tokens = |expr| get_tokens_with(expr, &syntax);
+
+        assert_eq!(
+            tokens("1.234,5*(0,5+1.000.000)"),
+            Ok(vec![
+                Token::Number(1234.5),
+                Token::Op(Operator::Mul),
+                Token::LPar,
+                Token::Number(0.5),
+                Token::Op(Operator::Add),
+                Token::Number(1000000.0),
+                Token::RPar,
+                Token::EOI
+            ])
+        );
+        assert_eq!(
+            get_program_tokens("x=if(a;1;2);x", &syntax).unwrap()[2..],
+            [
+                Token::Ident("if"),
+                Token::LPar,
+                Token::Ident("a"),
+                Token::Comma,
+                Token::Number(1.0),
+                Token::Comma,
+                Token::Number(2.0),
+                Token::RPar,
+                Token::Semicolon,
+                Token::Ident("x"),
+                Token::EOI
+            ]
+        );
+        assert_eq!(tokens("1.23"), Err(MexeError::MisplacedSeparator(1)));
+        assert_eq!(tokens("1.2345"), Err(MexeError::MisplacedSeparator(1)));
+        assert_eq!(
+            tokens("1,234.5"),
+            Err(MexeError::UnexpectedCharacter(b'.', 5))
+        );
+        assert_eq!(
+            tokens("1 , 2"),
+            Err(MexeError::UnexpectedCharacter(b',', 2))
+        );
+        assert_eq!(tokens(",5"), Err(MexeError::UnexpectedCharacter(b',', 0)));
+    }
+
+    #[test]
+    fn decimal_comma_groups_follow_a_short_first_group() {
+        let syntax = Syntax::new().number_format(NumberFormat::DecimalComma);
+        let tokens = |expr| get_tokens_with(expr, &syntax);
+
+        assert_eq!(tokens("123.456").unwrap()[0], Token::Number(123456.0));
+        assert_eq!(tokens("12345.678"), Err(MexeError::MisplacedSeparator(5)));
+        assert_eq!(tokens("1234.567,8"), Err(MexeError::MisplacedSeparator(4)));
+        assert_eq!(
+            tokens("2 * 1_000.000"),
+            Err(MexeError::MisplacedSeparator(9))
+        );
+    }
+
+    #[test]
+    fn lenient_literals() {
+        let lenient = Syntax::new().lenient_literals(true);
+        let comma = lenient.number_format(NumberFormat::DecimalComma);
+
+        assert_eq!(
+            get_tokens_with(".5+5.*(.25)", &lenient),
+            Ok(vec![
+                Token::Number(0.5),
+                Token::Op(Operator::Add),
+                Token::Number(5.0),
+                Token::Op(Operator::Mul),
+                Token::LPar,
+                Token::Number(0.25),
+                Token::RPar,
+                Token::EOI
+            ])
+        );
+        assert_eq!(
+            get_tokens_with(",5+1.000,", &comma),
+            Ok(vec![
+                Token::Number(0.5),
+                Token::Op(Operator::Add),
+                Token::Number(1000.0),
+                Token::EOI
+            ])
+        );
+        assert_eq!(
+            get_tokens_with(". 5", &lenient),
+            Err(MexeError::UnexpectedCharacter(b'.', 0))
+        );
+        assert_eq!(
+            get_tokens_with("5..", &lenient),
+            Err(MexeError::UnexpectedCharacter(b'.', 2))
+        );
+        assert_eq!(
+            get_tokens_with("x.", &lenient),
+            Err(MexeError::UnexpectedCharacter(b'.', 1))
+        );
+
+        assert_eq!(
+            get_tokens(".5"),
+            Err(MexeError::UnexpectedCharacter(b'.', 0))
+        );
+        assert_eq!(
+            get_tokens("1 + 5."),
+            Err(MexeError::UnexpectedCharacter(b'.', 5))
+        );
+        assert_eq!(
+            get_tokens("5.*2"),
+            Err(MexeError::UnexpectedCharacter(b'.', 1))
+        );
+    }
+
+    #[test]
+    fn whitespace_is_skipped() {
+        assert_eq!(
+            get_tokens("\t1 +\r\n\x0c\x0B 2\n"),
+            Ok(vec![
+                Token::Number(1.0),
+                Token::Op(Operator::Add),
+                Token::Number(2.0),
+                Token::EOI
+            ])
+        );
+        assert_eq!(
+            get_tokens("1\u{A0}+ 2"),
+            Err(MexeError::InvalidCharacter(1))
+        );
+
+        let unicode = Syntax::new().unicode_whitespace(true);
+        assert_eq!(
+            get_tokens_with("1\u{A0}+\u{2009}\u{3000}x", &unicode),
+            Ok(vec![
+                Token::Number(1.0),
+                Token::Op(Operator::Add),
+                Token::Ident("x"),
+                Token::EOI
+            ])
+        );
+        assert_eq!(
+            get_tokens_with("1 +\u{A0}é", &unicode),
+            Err(MexeError::InvalidCharacter(4))
+        );
+    }
+
+    #[test]
+    fn unicode_operators() {
+        assert_eq!(
+            get_tokens("6 × 2 ÷ 3 − 1·π"),
+            Ok(vec![
+                Token::Number(6.0),
+                Token::Op(Operator::Mul),
+                Token::Number(2.0),
+                Token::Op(Operator::Div),
+                Token::Number(3.0),
+                Token::Op(Operator::Sub),
+                Token::Number(1.0),
+                Token::Op(Operator::Mul),
+                Token::Ident("pi"),
+                Token::EOI
+            ])
+        );
+        assert_eq!(get_tokens("√4"), Err(MexeError::InvalidCharacter(0)));
+        assert_eq!(get_tokens("2 × 2²"), Err(MexeError::InvalidCharacter(5)));
+        assert_eq!(
+            get_tokens("π × 0x1G"),
+            Err(MexeError::InvalidDigit(b'G', 7))
+        );
+    }
+
+    #[test]
+    fn newlines_are_only_tokens_in_programs() {
+        assert_eq!(get_tokens("x\n"), Ok(vec![Token::Ident("x"), Token::EOI]));
+        assert_eq!(
+            get_program_tokens("x=1;\n", &Syntax::default()),
+            Ok(vec![
+                Token::Ident("x"),
+                Token::Assign,
+                Token::Number(1.0),
+                Token::Semicolon,
+                Token::Newline,
+                Token::EOI
+            ])
+        );
+        assert_eq!(
+            get_program_tokens("(1 +\n2)\n", &Syntax::default()),
+            Ok(vec![
+                Token::LPar,
+                Token::Number(1.0),
+                Token::Op(Operator::Add),
+                Token::Number(2.0),
+                Token::RPar,
+                Token::Newline,
+                Token::EOI
+            ])
+        );
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let expected = Ok(vec![
+            Token::Number(1.0),
+            Token::Op(Operator::Div),
+            Token::Number(2.0),
+            Token::EOI,
+        ]);
+
+        assert_eq!(get_tokens("1 / 2 # half"), expected);
+        assert_eq!(get_tokens("1/* one */ / /**/2#"), expected);
+        assert_eq!(get_tokens("1 /* x / *\n# y */ / 2"), expected);
+        assert_eq!(get_tokens("1 /* × */ / 2"), expected);
+        assert_eq!(
+            get_program_tokens("x # one\n", &Syntax::default()),
+            Ok(vec![Token::Ident("x"), Token::Newline, Token::EOI])
+        );
+        assert_eq!(
+            get_tokens("1 /* a */ + 2 /*/"),
+            Err(MexeError::UnterminatedComment(14))
+        );
+        assert_eq!(get_tokens("π /* ÷"), Err(MexeError::UnterminatedComment(2)));
+    }
+
+    #[test]
+    fn tokens_are_located() {
+        let position = |expression, index| position(expression, &Syntax::default(), true, index);
+
+        assert_eq!(position("1 +  x2 * 3", 2), 5);
+        assert_eq!(position("1 +", 2), 3);
+        assert_eq!(position("1\n+\n\tx", 4), 5);
+        assert_eq!(position("1 ×  x2 · 3", 2), 5);
+        assert_eq!(position("1 /* × */ + x", 2), 12);
+    }
+
+    #[test]
+    fn parse_errors_show_tokens_as_written() {
+        let syntax = Syntax::new().number_format(NumberFormat::DecimalComma);
+        let tokens = get_tokens_with("(1; 2)", &syntax).unwrap();
+
+        assert_eq!(
+            parse_error(
+                "(1; 2)",
+                &syntax,
+                false,
+                &tokens,
+                ParseError::UnexpectedToken(2)
+            ),
+            MexeError::UnexpectedToken(";".to_owned(), 2)
+        );
+        assert_eq!(
+            parse_error(
+                "1 + x",
+                &Syntax::default(),
+                false,
+                &get_tokens("1 + x").unwrap(),
+                ParseError::UseBeforeDefinition(2)
+            ),
+            MexeError::UseBeforeDefinition("x".to_owned(), 4)
+        );
+    }
 }
@@ -35,17 +35,54 @@
 //! - subtraction
 //! - multiplication
 //! - division
-//! - integers
+//! - the symbols `×`, `·`, `÷`, `−` for the operators and `π` for `pi`,
+//!   as pasted from documents (but not `√` or superscripts, as in `x²`,
+//!   since there are no square roots or powers)
+//! - integers, also in hexadecimal (`0x1F`), octal (`0o17`) and binary
+//!   (`0b1011`)
 //! - floats
+//! - digit separators, as in `1_000_000` (and `1'000'000` with [`Syntax`])
+//! - numbers with a decimal comma, as in `1.234,56` (with [`Syntax`] and
+//!   [`NumberFormat`])
 //! - parentheses
-//! - arbitrary whitespace
+//! - comments, `# until the end of the line` and `/* between delimiters */`
+//! - arbitrary whitespace, including tabs and newlines (and Unicode spaces
+//!   with [`Syntax`])
+//! - variables, simplification and symbolic differentiation (with [`parse`],
+//!   [`simplify`] and [`differentiate`])
+//! - formatting of long expressions over multiple lines (with [`format`])
+//! - LaTeX and MathML output (with [`to_latex`] and [`to_mathml`])
+//! - Reverse Polish Notation input and output (with [`eval_rpn`] and [`to_rpn`])
+//! - S-expression input and output (with [`parse_sexpr`] and [`to_sexpr`])
+//! - evaluation with values for the variables (with [`Context`])
+//! - comparison (`<`, `<=`, `>`, `>=`, `==`, `!=`) and logical (`&&`, `||`,
+//!   `!`) operators, whose results are booleans, used as conditions (or
+//!   returned as a [`Value`] with [`Context`])
+//! - bitwise (`&`, `|`, `xor`, `~`) and shift (`<<`, `>>`) operators on
+//!   64-bit integers
+//! - conditionals, `c ? a : b` or `if(c, a, b)`, which only evaluate the
+//!   branch that is taken
+//! - programs with assignments, such as `r = 2; pi * r * r` (with
+//!   [`eval_program`])
+//! - compilation to bytecode for repeated evaluation (with [`compile`]), which
+//!   can be saved and loaded (with [`Expr::to_bytes`] and [`Expr::from_bytes`])
+//!
+//! ## Optional Features
+//!
+//! - `serde`: implements `Serialize` and `Deserialize` for [`Ast`], [`Operator`],
+//!   [`Value`] and [`MexeError`], and adds the `serde` module, with helpers to read
+//!   numbers written as expressions in configuration files
+//! - `cli`: builds the `mexe` command-line calculator, which evaluates the
+//!   expression given as arguments or each line of the standard input (run
+//!   `mexe --help` for its options)
 //!
 //! ## Goals
 //!
 //! - Minimal
 //! - Fast: O(n)
 //! - No allocations if possible
-//! - We can assume the input is ASCII, and throw an error otherwise
+//! - We can assume the input is ASCII, except for a few math symbols, and throw
+//!   an error otherwise
 //! - Thoroughly tested
 //! - Maybe try to make it no-std
 //!
@@ -71,27 +108,172 @@
 //! * Crate: [crates.io](https://crates.io/crates/mexe) and [lib.rs](https://lib.rs/crates/mexe)
 //! * Repository: [Github](https://github.com/yds12/mexe)
 
+mod ast;
+mod bytecode;
+mod context;
+mod diff;
+mod encoding;
 mod error;
+mod eval;
+mod format;
+mod latex;
 mod lex;
+mod mathml;
 mod parser;
+mod rpn;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod sexpr;
+mod simplify;
+mod syntax;
 mod types;
 
+pub use ast::{Ast, AstDisplay, Spacing};
+pub use bytecode::{compile, Expr};
+pub use context::Context;
+pub use diff::differentiate;
 pub use error::{MexeError, Result};
-use types::{Operator, Token};
+pub use format::format;
+pub use latex::to_latex;
+pub use mathml::to_mathml;
+pub use rpn::{eval_rpn, to_rpn, RpnToken};
+pub use sexpr::{parse_sexpr, to_sexpr};
+pub use simplify::simplify;
+pub use syntax::{NumberFormat, Syntax};
+use types::Token;
+pub use types::{Operator, Value};
 
 /// Evaluates a numeric expression.
 ///
 /// The expression can contain integers, floats, sums, subtractions,
 /// multiplications, divisions and can use parentheses. Whitespace is ignored.
+/// Integers can also be combined with the bitwise operators `&`, `|`, `xor`
+/// and `~` and the shifts `<<` and `>>`, which bind more loosely than the
+/// arithmetic operators, as in C; their operands must be integers that fit in
+/// an `i64`. Comparisons, logical operators and conditionals are accepted as
+/// in [`parse`]: their booleans can be used as conditions, but the result must
+/// be a number. The constant `pi` can be used, and other variables need a
+/// [`Context`].
 /// Floating point numbers must be represented in the `X.Y` form, where `X` and
-/// `Y` are non-empty sequence of digits. The notation with the exponent is not
-/// currently supported.
+/// `Y` are non-empty sequence of digits, unless enabled with
+/// [`Syntax::lenient_literals`]. The notation with the exponent is not
+/// currently supported. Integers can also be written in hexadecimal, octal or
+/// binary, with the prefixes `0x`, `0o` and `0b` (in either case), up to
+/// 2^53 so that their value is exact. Digits can be grouped with underscores,
+/// as in `1_000`, and with apostrophes if enabled with
+/// [`Syntax::apostrophes`]. Comments are skipped: `#` until the end of the
+/// line, and `/*` until `*/`.
 ///
 /// `T`: type of the expression. Usually a `&str` or a `String`.
 ///
 /// ```
 /// # fn main() -> Result<(), mexe::MexeError> {
 /// let x = mexe::eval("2 * (1 + 1)")?;
+/// assert_eq!(mexe::eval("0x1F + 0o17 + 0b1011")?, 57.0);
+/// assert_eq!(mexe::eval("1 << 4 | 3")?, 19.0);
+/// assert_eq!(mexe::eval("2 * pi > 6 && 1 != 2 ? 1 : 0")?, 1.0);
+/// assert_eq!(mexe::eval("60 * 24 /* minutes per day */ # 1440")?, 1440.0);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// This function will return [`MexeError::TypeMismatch`] if an operand of a
+/// bitwise operator is not an integer or the result is not a number,
+/// [`MexeError::UndefinedVariable`] if a variable other than `pi` is used,
+/// [`MexeError::MisplacedSeparator`] if a digit separator is not between two
+/// digits, [`MexeError::UnterminatedComment`] if a `/*` comment is not
+/// closed, and other [`MexeError`]s if the input is not a valid arithmetic
+/// expression.
+pub fn eval<T>(expression: T) -> Result<f64>
+where
+    T: AsRef<str>,
+{
+    Syntax::new().eval(expression)
+}
+
+/// Evaluates a numeric expression like [`eval`], but division by zero is an
+/// error instead of resulting in infinity or NaN.
+///
+/// `T`: type of the expression. Usually a `&str` or a `String`.
+///
+/// ```
+/// assert_eq!(mexe::eval_strict("1 / 4"), Ok(0.25));
+/// assert_eq!(
+///     mexe::eval_strict("1 / (2 - 2)"),
+///     Err(mexe::MexeError::DivisionByZero)
+/// );
+///
+/// // only the branch that is taken is evaluated
+/// assert_eq!(mexe::eval_strict("2 - 2 == 0 ? 0 : 1 / (2 - 2)"), Ok(0.0));
+/// ```
+///
+/// # Errors
+///
+/// This function will return [`MexeError::DivisionByZero`] if the expression
+/// divides by zero, [`MexeError::TypeMismatch`] if its result is not a number,
+/// and other [`MexeError`]s if the input is not a valid arithmetic expression.
+pub fn eval_strict<T>(expression: T) -> Result<f64>
+where
+    T: AsRef<str>,
+{
+    eval::eval_ast(&parse(expression)?, &Context::new(), true)?.try_into()
+}
+
+/// Evaluates a program: statements separated by `;` or newlines, where each
+/// statement is either an expression or an assignment of an expression to a
+/// variable, such as `x = 2 * 3`. The result is the value of the last
+/// statement. A newline inside parentheses does not end a statement.
+///
+/// Variables are written as in [`parse`], and must be assigned before they
+/// are used. The constant `pi` can be used without being assigned. Use a
+/// [`Context`] to give values to variables before evaluating the program.
+///
+/// `T`: type of the program. Usually a `&str` or a `String`.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let area = mexe::eval_program("r = 2; area = pi * r * r; area * 2")?;
+/// assert_eq!(area, std::f64::consts::PI * 8.0);
+///
+/// assert_eq!(mexe::eval_program("a = 1\nb = a + 1\n\na + b")?, 3.0);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// This function will return [`MexeError::UseBeforeDefinition`] if a variable
+/// is used before it is assigned, [`MexeError::TypeMismatch`] if the result is
+/// not a number, and other [`MexeError`]s if the input is not a valid program.
+pub fn eval_program<T>(program: T) -> Result<f64>
+where
+    T: AsRef<str>,
+{
+    Context::new().eval_program(program)?.try_into()
+}
+
+/// Parses an expression into an [`Ast`] without evaluating it.
+///
+/// Besides numbers and the arithmetic and bitwise operators of [`eval`], the
+/// expression can contain variables: sequences of ASCII letters, digits and
+/// underscores that do not start with a digit. It can also contain the
+/// comparison and logical operators, which bind more loosely than the
+/// arithmetic ones and the shifts, from comparisons (`<`, `<=`, `>`, `>=`), to
+/// equality (`==`, `!=`), then, after the bitwise `&`, `xor` and `|`, to `&&`
+/// and `||`, and the logical negation `!`. Conditionals, written `c ? a : b`
+/// or `if(c, a, b)`, bind the loosest of all, and are right-associative.
+///
+/// `T`: type of the expression. Usually a `&str` or a `String`.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let ast = mexe::parse("2 * (x + 1)")?;
+/// assert_eq!(ast.to_string(), "2 * (x + 1)");
+///
+/// let ast = mexe::parse("!(a < b + 1 && c == 0)")?;
+/// assert_eq!(ast.to_string(), "!(a < b + 1 && c == 0)");
 /// # Ok(())
 /// # }
 /// ```
@@ -100,12 +282,11 @@ use types::{Operator, Token};
 ///
 /// This function will return a [`MexeError`] if the input is not a valid
 /// arithmetic expression.
-pub fn eval<T>(expression: T) -> Result<f64>
+pub fn parse<T>(expression: T) -> Result<Ast>
 where
     T: AsRef<str>,
 {
-    let tokens = lex::get_tokens(expression.as_ref())?;
-    parser::parse_and_evaluate(tokens)
+    Syntax::new().parse(expression)
 }
 
 /// Evaluates a numeric expression assuming it is just one operation between
@@ -208,6 +389,35 @@ mod tests {
         float_eq!(0.45, eval("0.15 + 0.15 + 0.15").unwrap());
     }
 
+    #[test]
+    fn eval_skips_whitespace() {
+        float_eq!(3.0, eval("\t1 +\r\n  2\n").unwrap());
+        float_eq!(3.0, eval("1\x0B+\x0C2").unwrap());
+    }
+
+    #[test]
+    fn eval_accepts_unicode_symbols() {
+        float_eq!(3.0, eval("6 × 2 ÷ 3 − 1·1").unwrap());
+        float_eq!(std::f64::consts::PI, eval("π").unwrap());
+        float_eq!(2.0 * std::f64::consts::PI, eval("2 × π").unwrap());
+
+        // there are no square roots or powers
+        assert_eq!(eval("√4"), Err(MexeError::InvalidCharacter(0)));
+        assert_eq!(eval("2 × 3²"), Err(MexeError::InvalidCharacter(5)));
+    }
+
+    #[test]
+    fn eval_accepts_bitwise_operators() {
+        float_eq!(19.0, eval("(1 << 4) | 3").unwrap());
+        float_eq!(-1.0, eval("~0").unwrap());
+        float_eq!(4.0, eval("5 xor 1").unwrap());
+        float_eq!(3.0, eval("6 & 3 xor 1 | 1 - 1").unwrap());
+        float_eq!(8.0, eval("-(~7)").unwrap());
+        float_eq!(8.0, eval("-~7").unwrap());
+        float_eq!(-1.0, eval("~-1 - 1").unwrap());
+        float_eq!(0.0, eval("1 - -~0").unwrap());
+    }
+
     #[test]
     fn test_eval_failures() {
         let exprs = ["(((1", "((1", "(1", "1)))", "1))", "1)"];
@@ -221,6 +431,251 @@ mod tests {
 
     #[test]
     fn correct_errors_are_returned() {
-        assert_eq!(eval("1++"), Err(MexeError::UnexpectedToken("+".to_owned())));
+        assert_eq!(
+            eval("1++"),
+            Err(MexeError::UnexpectedToken("+".to_owned(), 2))
+        );
+        assert_eq!(
+            eval("1 + x"),
+            Err(MexeError::UndefinedVariable("x".to_owned()))
+        );
+    }
+
+    #[test]
+    fn unexpected_tokens_are_located() {
+        let syntax = Syntax::new();
+        let unexpected = |expression: &str, token: &str, index| {
+            let err = syntax.parse(expression).unwrap_err();
+            assert_eq!(
+                err,
+                MexeError::UnexpectedToken(token.to_owned(), index),
+                "{}",
+                expression
+            );
+            assert_eq!(err.index(), Some(index), "{}", expression);
+            assert_eq!(syntax.eval(expression), Err(err), "{}", expression);
+        };
+
+        unexpected("1++", "+", 2);
+        unexpected("(1 + 2", "EOI", 6);
+        unexpected("(x) y", "y", 4);
+        unexpected("1 & ~", "EOI", 5);
+        unexpected("a ? b c", "c", 6);
+        unexpected("if(a, b)", ")", 7);
+        unexpected("2 × (3 ÷ 4", "EOI", 10);
+
+        let syntax = Syntax::new().apostrophes(true);
+        assert_eq!(
+            syntax.eval("1'000 +"),
+            Err(MexeError::UnexpectedToken("EOI".to_owned(), 7))
+        );
+    }
+
+    #[test]
+    fn other_errors_are_returned() {
+        assert_eq!(
+            eval("1 < 2"),
+            Err(MexeError::TypeMismatch(
+                "expected a number, found a boolean".to_owned()
+            ))
+        );
+        assert_eq!(parse("a < b $ c"), Err(MexeError::InvalidCharacter(6)));
+        assert_eq!(
+            eval("0b101 + 0b102"),
+            Err(MexeError::InvalidDigit(b'2', 12))
+        );
+        assert_eq!(eval("1_000 * 1__0"), Err(MexeError::MisplacedSeparator(10)));
+        assert_eq!(
+            eval("1.5 & 1"),
+            Err(MexeError::TypeMismatch(
+                "`&` cannot be applied to 1.5, which is not a 64-bit integer".to_owned()
+            ))
+        );
+        assert_eq!(
+            eval_strict("1 >= 2"),
+            Err(MexeError::TypeMismatch(
+                "expected a number, found a boolean".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn eval_accepts_comparisons_and_logical_operators() {
+        let exprs = [
+            "1 < 2",
+            "!(1 == 2)",
+            "1 <= 2 && 3 != 4 || 0 > 1",
+            "(1 < 2) + 1",
+            "!1",
+            "1 < ",
+        ];
+
+        for expr in exprs.iter() {
+            assert_eq!(eval(expr), eval_strict(expr), "{}", expr);
+        }
+
+        assert_eq!(
+            eval("!(1 == 2)"),
+            Err(MexeError::TypeMismatch(
+                "expected a number, found a boolean".to_owned()
+            ))
+        );
+        float_eq!(2.0 * std::f64::consts::PI, eval("2 * pi").unwrap());
+    }
+
+    #[test]
+    fn eval_accepts_conditionals() {
+        assert_eq!(eval("1 < 2 ? 3 : 4"), Ok(3.0));
+        assert_eq!(eval("if(1, 2, 3)"), eval_strict("if(1, 2, 3)"));
+        assert_eq!(eval("if(2 > 3, 2, 3)"), Ok(3.0));
+        assert_eq!(eval("1 > 2 ? 1 : 2 > 1 ? 2 : 3"), Ok(2.0));
+        assert_eq!(eval("-(1 == 1 ? 5 : 6) * 2"), Ok(-10.0));
+
+        // only the branch that is taken is evaluated
+        assert_eq!(eval("1 < 2 ? 1 : 1.5 & 1"), Ok(1.0));
+        assert_eq!(eval("if(1 > 2, 1.5 | 1, 7)"), Ok(7.0));
+        assert_eq!(
+            eval("1 > 2 ? 1 : 1.5 & 1"),
+            Err(MexeError::TypeMismatch(
+                "`&` cannot be applied to 1.5, which is not a 64-bit integer".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn eval_does_not_evaluate_what_it_skips() {
+        assert_eq!(eval("1 > 2 ? x : 3"), Ok(3.0));
+        assert_eq!(eval("if(1 < 2, 4, y / 0)"), Ok(4.0));
+        assert_eq!(eval("1 == 1 || x ? 5 : 6"), Ok(5.0));
+        assert_eq!(eval("1 == 2 && 1.5 & 1 ? 1 : 2"), Ok(2.0));
+        assert_eq!(
+            eval("1 < 2 ? x : 3"),
+            Err(MexeError::UndefinedVariable("x".to_owned()))
+        );
+
+        // syntax errors are still reported first
+        assert_eq!(
+            eval("x + (1"),
+            Err(MexeError::UnexpectedToken("EOI".to_owned(), 6))
+        );
+        assert_eq!(
+            eval("1 > 2 ? 1 : 2 +"),
+            Err(MexeError::UnexpectedToken("EOI".to_owned(), 15))
+        );
+    }
+
+    #[test]
+    fn eval_accepts_long_and_deeply_nested_expressions() {
+        let sum = vec!["1"; 1000].join(" + ");
+        assert_eq!(eval(&sum), Ok(1000.0));
+
+        let nested = format!("{}1{}", "(".repeat(1000), ")".repeat(1000));
+        assert_eq!(eval(&nested), Ok(1.0));
+    }
+
+    #[test]
+    fn test_eval_program() {
+        float_eq!(6.0, eval_program("x = 2; y = x * 3").unwrap());
+        float_eq!(5.0, eval_program(";x = 2;; x + 3;\n").unwrap());
+        float_eq!(-2.0, eval_program("x = 1\nx = x - 3\nx").unwrap());
+        float_eq!(3.0, eval_program("x = (1 +\n 2)\nx").unwrap());
+        float_eq!(
+            3.0,
+            eval_program("# x is one\nx = 1 # not 2\n/* double:\n */ x * 3").unwrap()
+        );
+
+        assert_eq!(
+            eval_program("x = y + 1; y = 2"),
+            Err(MexeError::UseBeforeDefinition("y".to_owned(), 4))
+        );
+        assert_eq!(
+            eval_program("x = x"),
+            Err(MexeError::UseBeforeDefinition("x".to_owned(), 4))
+        );
+        assert_eq!(eval_program("if = 2; if(if > 1, if, 0)"), Ok(2.0));
+        assert_eq!(
+            eval_program("x = 1 # one\ny = /* two"),
+            Err(MexeError::UnterminatedComment(16))
+        );
+    }
+
+    #[test]
+    fn unexpected_tokens_are_located_in_programs() {
+        let cases = [
+            ("x = 1 y = 2", "y", 6),
+            ("x = ; 1", ";", 4),
+            (" ;\n", "EOI", 3),
+            ("x = 1\n  y +* 2", "*", 11),
+            ("1 = 2", "=", 2),
+        ];
+
+        for (program, token, index) in cases.iter() {
+            let mut context = Context::new();
+            assert_eq!(
+                context.eval_program(program),
+                Err(MexeError::UnexpectedToken(token.to_string(), *index)),
+                "{}",
+                program
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let exprs = [
+            "1",
+            "-1",
+            "x",
+            "-x",
+            "-(x + 1)",
+            "1 - 2 - 3",
+            "1 - (2 - 3)",
+            "1 / (2 * 3)",
+            "(1 + 2) * -3",
+            "-(2) * -(-3) - -4",
+            "x * y + 2 / -z",
+            "a < b + 1 || c",
+            "a == (b != c)",
+            "!(a && b) == !-c",
+            "a || b && c <= d",
+            "a ? b ? 1 : 2 : c ? 3 : 4",
+            "(a ? b : c) ? -(d ? 1 : 2) : !(e ? f : g)",
+            "x * (a ? 1 : 2) < 3 ? x : y",
+            "a | b xor c & d",
+            "(a | b) xor c & d == 1",
+            "1 << x + 1 < y && ~(a >> 2) != -b",
+            "(a & b) + 1",
+            "~~a << (b < c)",
+            "-~a * -!b",
+            "-!(a && b) || -~-~c",
+            "-~(a + 1) - -!-(1)",
+        ];
+
+        for expr in exprs.iter() {
+            assert_eq!(&parse(expr).unwrap().to_string(), expr);
+        }
+
+        assert_eq!(parse("((x)) + (1 * 2)").unwrap().to_string(), "x + 1 * 2");
+        assert_eq!(parse("-(~a) * -(!b)").unwrap().to_string(), "-~a * -!b");
+        assert_eq!(
+            parse("if(a, b, if(c, d, e))").unwrap(),
+            parse("a ? b : c ? d : e").unwrap()
+        );
+        assert_eq!(
+            parse("1 - (-3) * -(x)")
+                .unwrap()
+                .display(Spacing::Compact)
+                .to_string(),
+            "1--3*-x"
+        );
+        assert_eq!(
+            parse("a  xor  (b & c<<1)")
+                .unwrap()
+                .display(Spacing::Compact)
+                .to_string(),
+            "a xor b&c<<1"
+        );
+        assert!(parse("(x").is_err());
+        assert!(parse("x y").is_err());
     }
 }
@@ -0,0 +1,209 @@
+use crate::latex::needs_parens;
+use crate::{Ast, Operator};
+
+const MINUS: &str = "<mo>&#x2212;</mo>";
+
+/// Renders an expression as Presentation MathML.
+///
+/// The result is a `<math>` element that browsers can display directly.
+/// Divisions become `<mfrac>`, multiplications use the dot operator, and
+/// minus signs (binary or unary) use the proper minus character, `pi` is
+/// written as `&pi;`, and conditionals are written as a table of cases after a
+/// brace. Parentheses
+/// follow the same rules as [`to_latex`](crate::to_latex): they are only
+/// inserted where the expression would otherwise be read differently.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let ast = mexe::parse("-(x - 1) / 2")?;
+/// assert_eq!(
+///     mexe::to_mathml(&ast),
+///     "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">\
+///      <mfrac>\
+///      <mrow><mo>&#x2212;</mo><mrow><mo>(</mo>\
+///      <mrow><mi>x</mi><mo>&#x2212;</mo><mn>1</mn></mrow>\
+///      <mo>)</mo></mrow></mrow>\
+///      <mn>2</mn>\
+///      </mfrac>\
+///      </math>"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_mathml(ast: &Ast) -> String {
+    let mut mathml = String::from("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">");
+    write_mathml(ast, &mut mathml);
+    mathml.push_str("</math>");
+    mathml
+}
+
+fn write_mathml(ast: &Ast, out: &mut String) {
+    match ast {
+        Ast::Number(n) if n.is_sign_negative() => {
+            out.push_str("<mrow>");
+            out.push_str(MINUS);
+            out.push_str(&format!("<mn>{}</mn>", -n));
+            out.push_str("</mrow>");
+        }
+        Ast::Number(n) => out.push_str(&format!("<mn>{}</mn>", n)),
+        Ast::Var(name) if name == "pi" => out.push_str("<mi>&pi;</mi>"),
+        Ast::Var(name) => out.push_str(&format!("<mi>{}</mi>", name)),
+        Ast::Neg(inner) => {
+            out.push_str("<mrow>");
+            out.push_str(MINUS);
+            write_operand(inner, inner.is_sum() || inner.is_negative(), out);
+            out.push_str("</mrow>");
+        }
+        Ast::Not(inner) => {
+            out.push_str("<mrow><mo>&#xAC;</mo>");
+            write_operand(inner, matches!(inner.as_ref(), Ast::Binary(..)), out);
+            out.push_str("</mrow>");
+        }
+        Ast::BitNot(inner) => {
+            out.push_str("<mrow><mo>~</mo>");
+            write_operand(inner, matches!(inner.as_ref(), Ast::Binary(..)), out);
+            out.push_str("</mrow>");
+        }
+        Ast::Cond(cond, then, otherwise) => {
+            out.push_str("<mrow><mo>{</mo><mtable><mtr><mtd>");
+            write_mathml(then, out);
+            out.push_str("</mtd><mtd><mtext>if&#xA0;</mtext>");
+            write_mathml(cond, out);
+            out.push_str("</mtd></mtr><mtr><mtd>");
+            write_mathml(otherwise, out);
+            out.push_str("</mtd><mtd><mtext>otherwise</mtext></mtd></mtr></mtable></mrow>");
+        }
+        Ast::Binary(Operator::Div, num, den) => {
+            out.push_str("<mfrac>");
+            write_mathml(num, out);
+            write_mathml(den, out);
+            out.push_str("</mfrac>");
+        }
+        Ast::Binary(op, lhs, rhs) => {
+            let (lhs_parens, rhs_parens) = needs_parens(*op, lhs, rhs);
+            let op = match op {
+                Operator::Add => "<mo>+</mo>",
+                Operator::Sub => MINUS,
+                Operator::Mul | Operator::Div => "<mo>&#x22C5;</mo>",
+                Operator::Lt => "<mo>&lt;</mo>",
+                Operator::Le => "<mo>&#x2264;</mo>",
+                Operator::Gt => "<mo>&gt;</mo>",
+                Operator::Ge => "<mo>&#x2265;</mo>",
+                Operator::Eq => "<mo>=</mo>",
+                Operator::Ne => "<mo>&#x2260;</mo>",
+                Operator::And => "<mo>&#x2227;</mo>",
+                Operator::Or => "<mo>&#x2228;</mo>",
+                Operator::BitAnd => "<mo>&amp;</mo>",
+                Operator::BitOr => "<mo>|</mo>",
+                Operator::BitXor => "<mo>&#x2295;</mo>",
+                Operator::Shl => "<mo>&#x226A;</mo>",
+                Operator::Shr => "<mo>&#x226B;</mo>",
+            };
+
+            out.push_str("<mrow>");
+            write_operand(lhs, lhs_parens, out);
+            out.push_str(op);
+            write_operand(rhs, rhs_parens, out);
+            out.push_str("</mrow>");
+        }
+    }
+}
+
+fn write_operand(ast: &Ast, parens: bool, out: &mut String) {
+    if parens {
+        out.push_str("<mrow><mo>(</mo>");
+        write_mathml(ast, out);
+        out.push_str("<mo>)</mo></mrow>");
+    } else {
+        write_mathml(ast, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn mathml(expr: &str) -> String {
+        let mathml = to_mathml(&parse(expr).unwrap());
+        let start = "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">";
+        mathml[start.len()..mathml.len() - "</math>".len()].to_owned()
+    }
+
+    #[test]
+    fn test_to_mathml() {
+        assert_eq!(mathml("1.5"), "<mn>1.5</mn>");
+        assert_eq!(mathml("-1"), "<mrow><mo>&#x2212;</mo><mn>1</mn></mrow>");
+        assert_eq!(mathml("-x"), "<mrow><mo>&#x2212;</mo><mi>x</mi></mrow>");
+        assert_eq!(
+            mathml("a * -b"),
+            "<mrow><mi>a</mi><mo>&#x22C5;</mo>\
+             <mrow><mo>(</mo><mrow><mo>&#x2212;</mo><mi>b</mi></mrow><mo>)</mo></mrow></mrow>"
+        );
+        assert_eq!(
+            mathml("(a + b) * c"),
+            "<mrow><mrow><mo>(</mo><mrow><mi>a</mi><mo>+</mo><mi>b</mi></mrow><mo>)</mo></mrow>\
+             <mo>&#x22C5;</mo><mi>c</mi></mrow>"
+        );
+        assert_eq!(
+            mathml("a / (b / c)"),
+            "<mfrac><mi>a</mi><mfrac><mi>b</mi><mi>c</mi></mfrac></mfrac>"
+        );
+        assert_eq!(
+            mathml("pi * r"),
+            "<mrow><mi>&pi;</mi><mo>&#x22C5;</mo><mi>r</mi></mrow>"
+        );
+    }
+
+    #[test]
+    fn comparisons_and_logical_operators() {
+        assert_eq!(
+            mathml("a < 1 && !b"),
+            "<mrow><mrow><mi>a</mi><mo>&lt;</mo><mn>1</mn></mrow>\
+             <mo>&#x2227;</mo><mrow><mo>&#xAC;</mo><mi>b</mi></mrow></mrow>"
+        );
+        assert_eq!(
+            mathml("(a >= b) != c"),
+            "<mrow><mrow><mi>a</mi><mo>&#x2265;</mo><mi>b</mi></mrow>\
+             <mo>&#x2260;</mo><mi>c</mi></mrow>"
+        );
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        assert_eq!(
+            mathml("a & ~b xor 1 << c"),
+            "<mrow><mrow><mi>a</mi><mo>&amp;</mo><mrow><mo>~</mo><mi>b</mi></mrow></mrow>\
+             <mo>&#x2295;</mo><mrow><mn>1</mn><mo>&#x226A;</mo><mi>c</mi></mrow></mrow>"
+        );
+    }
+
+    #[test]
+    fn conditionals_are_cases() {
+        assert_eq!(
+            mathml("if(a, 1, 2)"),
+            "<mrow><mo>{</mo><mtable>\
+             <mtr><mtd><mn>1</mn></mtd><mtd><mtext>if&#xA0;</mtext><mi>a</mi></mtd></mtr>\
+             <mtr><mtd><mn>2</mn></mtd><mtd><mtext>otherwise</mtext></mtd></mtr>\
+             </mtable></mrow>"
+        );
+    }
+
+    #[test]
+    fn tags_are_balanced() {
+        let exprs = [
+            "-(-(a - -1) / (b * -(c / -d)))",
+            "((9 + 7.07) * ((((1.5 - 0.001 + 0.005) * (0.7 - 0.33))) / (2.5 + 3 * 0.1) + 6))",
+        ];
+
+        for expr in exprs.iter() {
+            let mathml = mathml(expr);
+
+            for tag in ["mrow", "mfrac", "mo", "mn", "mi"].iter() {
+                let open = mathml.matches(&format!("<{}>", tag)).count();
+                let close = mathml.matches(&format!("</{}>", tag)).count();
+                assert_eq!(open, close, "{}", tag);
+            }
+        }
+    }
+}
@@ -1,109 +1,638 @@
-use crate::{MexeError, Operator, Result, Token};
+use crate::{context, eval, Ast, MexeError, Operator, Token, Value};
 
-pub(crate) fn parse_and_evaluate(input: Vec<Token>) -> Result<f64> {
-    match ll_parse_expr(&input[..]) {
+// Where the tokens stop following the grammar, with the index of the token.
+// The tokens are needed to turn it into a `MexeError`, see
+// `lex::parse_error`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ParseError {
+    // a token that the grammar does not accept here
+    UnexpectedToken(usize),
+    // a variable used in a program before it is assigned
+    UseBeforeDefinition(usize),
+}
+
+// The parsing functions only see the rest of the input, so they report an
+// unexpected token with the number of tokens left from it, which is turned
+// into its index once parsing stops.
+struct Unexpected(usize);
+
+type Parsed<'a, T> = Result<(T, &'a [Token<'a>]), Unexpected>;
+
+pub(crate) fn parse_tree(tokens: &[Token]) -> Result<Ast, ParseError> {
+    match ll_build_cond(tokens) {
+        Ok((ast, [Token::EOI])) => Ok(ast),
+        // finished parsing but there's something left
+        Ok((_, input)) => Err(unexpected_at(tokens, input.len())),
+        Err(Unexpected(left)) => Err(unexpected_at(tokens, left)),
+    }
+}
+
+// Evaluates the tokens while parsing them, without building a tree, to a
+// number. An error found while evaluating is stored in `failed`, which the
+// caller only reports if parsing succeeds, so that a syntax error after it is
+// reported instead, as `parse_tree` would. The result is returned without the
+// error, which keeps it small.
+pub(crate) fn parse_and_evaluate(
+    tokens: &[Token],
+    failed: &mut Option<MexeError>,
+) -> Result<f64, ParseError> {
+    match ll_eval_binary(tokens, CONDITIONAL, false, failed) {
+        Ok((val, [Token::EOI])) => match f64::try_from(Value::from(val)) {
+            Ok(n) => Ok(n),
+            // a boolean is an error, unless an earlier one was found
+            Err(err) => {
+                failed.get_or_insert(err);
+                Ok(0.0)
+            }
+        },
         // finished parsing but there's something left
-        Ok((Some(_), input)) if !is_over(input) => {
-            Err(MexeError::UnexpectedToken(input[0].to_string()))
+        Ok((_, input)) => Err(unexpected_at(tokens, input.len())),
+        Err(Unexpected(left)) => Err(unexpected_at(tokens, left)),
+    }
+}
+
+// The statements of a program, each with the name of the variable it assigns,
+// if any. Variables are checked while parsing, so that using one before it is
+// assigned (or `defined` elsewhere) is reported with its position.
+//
+// P  -> S P'
+// P' -> ; S P'
+// P' -> ε
+// S  -> v = C
+// S  -> C
+// S  -> ε
+//
+// where newlines outside of parentheses are also accepted as `;`.
+pub(crate) fn parse_program<'a>(
+    tokens: &[Token<'a>],
+    defined: impl Fn(&str) -> bool,
+) -> Result<Vec<(Option<&'a str>, Ast)>, ParseError> {
+    let mut statements = Vec::new();
+    let mut assigned = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let (name, start) = match (tokens[pos], tokens.get(pos + 1)) {
+            (Token::Ident(name), Some(Token::Assign)) => (Some(name), pos + 2),
+            _ => (None, pos),
+        };
+
+        pos = match (name, tokens[start]) {
+            (None, Token::Semicolon | Token::Newline | Token::EOI) => start,
+            _ => {
+                let (ast, rest) = ll_build_cond(&tokens[start..])
+                    .map_err(|Unexpected(left)| unexpected_at(tokens, left))?;
+                let end = tokens.len() - rest.len();
+
+                for (i, token) in tokens.iter().enumerate().take(end).skip(start) {
+                    match (token, tokens[i + 1]) {
+                        // `if(...)` is not a variable
+                        (Token::Ident(_), Token::LPar) => (),
+                        (Token::Ident(var), _) if !assigned.contains(var) && !defined(var) => {
+                            return Err(ParseError::UseBeforeDefinition(i));
+                        }
+                        _ => (),
+                    }
+                }
+
+                if let Some(name) = name {
+                    assigned.push(name);
+                }
+
+                statements.push((name, ast));
+                end
+            }
+        };
+
+        match tokens[pos] {
+            Token::Semicolon | Token::Newline => pos += 1,
+            Token::EOI if statements.is_empty() => return Err(ParseError::UnexpectedToken(pos)),
+            Token::EOI => return Ok(statements),
+            _ => return Err(ParseError::UnexpectedToken(pos)),
+        }
+    }
+}
+
+fn unexpected(input: &[Token]) -> Unexpected {
+    Unexpected(input.len())
+}
+
+// The error for the token that is `left` tokens away from the end.
+fn unexpected_at(tokens: &[Token], left: usize) -> ParseError {
+    ParseError::UnexpectedToken(tokens.len() - left)
+}
+
+// The `ll_build_*` functions parse the tokens into an [`Ast`], with one
+// function per rule of the LL(1) grammar below, from the loosest operators
+// to the tightest: conditionals, logical operators, the bitwise ones, which
+// bind more loosely than equality and comparisons as in C, then the shifts
+// and the arithmetic operators. Only the branch of a conditional that is taken
+// is evaluated, as the tree is evaluated afterwards. See the grammar in the
+// readme.
+
+// C  -> O C'
+// C' -> ? C : C
+// C' -> ε
+fn ll_build_cond<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    let (cond, input) = ll_build_or(input)?;
+
+    match input[0] {
+        Token::Question => {
+            let (then, input) = ll_build_cond(&input[1..])?;
+            let input = ll_build_expect(Token::Colon, input)?;
+            let (otherwise, input) = ll_build_cond(input)?;
+            Ok((Ast::cond(cond, then, otherwise), input))
+        }
+        _ => Ok((cond, input)),
+    }
+}
+
+// O  -> A O'
+fn ll_build_or<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    let (ast, input) = ll_build_and(input)?;
+    ll_build_orexpr(ast, input)
+}
+
+// O' -> || A O'
+// O' -> ε
+fn ll_build_orexpr<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(Operator::Or) => {
+            let (rhs, input) = ll_build_and(&input[1..])?;
+            ll_build_orexpr(Ast::binary(Operator::Or, lhs, rhs), input)
+        }
+        _ => Ok((lhs, input)),
+    }
+}
+
+// A  -> B A'
+fn ll_build_and<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    let (ast, input) = ll_build_bitor(input)?;
+    ll_build_andexpr(ast, input)
+}
+
+// A' -> && B A'
+// A' -> ε
+fn ll_build_andexpr<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(Operator::And) => {
+            let (rhs, input) = ll_build_bitor(&input[1..])?;
+            ll_build_andexpr(Ast::binary(Operator::And, lhs, rhs), input)
+        }
+        _ => Ok((lhs, input)),
+    }
+}
+
+// B  -> X B'
+fn ll_build_bitor<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    let (ast, input) = ll_build_xor(input)?;
+    ll_build_bitorexpr(ast, input)
+}
+
+// B' -> | X B'
+// B' -> ε
+fn ll_build_bitorexpr<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(Operator::BitOr) => {
+            let (rhs, input) = ll_build_xor(&input[1..])?;
+            ll_build_bitorexpr(Ast::binary(Operator::BitOr, lhs, rhs), input)
+        }
+        _ => Ok((lhs, input)),
+    }
+}
+
+// X  -> D X'
+fn ll_build_xor<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    let (ast, input) = ll_build_bitand(input)?;
+    ll_build_xorexpr(ast, input)
+}
+
+// X' -> xor D X'
+// X' -> ε
+fn ll_build_xorexpr<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(Operator::BitXor) => {
+            let (rhs, input) = ll_build_bitand(&input[1..])?;
+            ll_build_xorexpr(Ast::binary(Operator::BitXor, lhs, rhs), input)
         }
-        Ok((Some(val), _)) => Ok(val),
-        // if value is `None` the parse should have failed earlier (should never happen)
-        Ok((None, _)) => Err(MexeError::InternalParserError),
-        Err(err) => Err(err),
+        _ => Ok((lhs, input)),
     }
 }
 
-fn is_over(input: &[Token]) -> bool {
-    input.len() == 1 && input[0] == Token::EOI
+// D  -> Q D'
+fn ll_build_bitand<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    let (ast, input) = ll_build_equality(input)?;
+    ll_build_bitandexpr(ast, input)
+}
+
+// D' -> & Q D'
+// D' -> ε
+fn ll_build_bitandexpr<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(Operator::BitAnd) => {
+            let (rhs, input) = ll_build_equality(&input[1..])?;
+            ll_build_bitandexpr(Ast::binary(Operator::BitAnd, lhs, rhs), input)
+        }
+        _ => Ok((lhs, input)),
+    }
+}
+
+// Q  -> R Q'
+fn ll_build_equality<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    let (ast, input) = ll_build_comparison(input)?;
+    ll_build_eqexpr(ast, input)
+}
+
+// Q' -> == R Q'
+// Q' -> != R Q'
+// Q' -> ε
+fn ll_build_eqexpr<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(op @ (Operator::Eq | Operator::Ne)) => {
+            let (rhs, input) = ll_build_comparison(&input[1..])?;
+            ll_build_eqexpr(Ast::binary(op, lhs, rhs), input)
+        }
+        _ => Ok((lhs, input)),
+    }
+}
+
+// R  -> H R'
+fn ll_build_comparison<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    let (ast, input) = ll_build_shift(input)?;
+    ll_build_cmpexpr(ast, input)
+}
+
+// R' -> < H R'
+// R' -> <= H R'
+// R' -> > H R'
+// R' -> >= H R'
+// R' -> ε
+fn ll_build_cmpexpr<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(op @ (Operator::Lt | Operator::Le | Operator::Gt | Operator::Ge)) => {
+            let (rhs, input) = ll_build_shift(&input[1..])?;
+            ll_build_cmpexpr(Ast::binary(op, lhs, rhs), input)
+        }
+        _ => Ok((lhs, input)),
+    }
+}
+
+// H  -> E H'
+fn ll_build_shift<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    let (ast, input) = ll_build_expr(input)?;
+    ll_build_shiftexpr(ast, input)
+}
+
+// H' -> << E H'
+// H' -> >> E H'
+// H' -> ε
+fn ll_build_shiftexpr<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(op @ (Operator::Shl | Operator::Shr)) => {
+            let (rhs, input) = ll_build_expr(&input[1..])?;
+            ll_build_shiftexpr(Ast::binary(op, lhs, rhs), input)
+        }
+        _ => Ok((lhs, input)),
+    }
 }
 
 // E  -> T E'
-fn ll_parse_expr(input: &[Token]) -> Result<(Option<f64>, &[Token])> {
+fn ll_build_expr<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
     match input[0] {
-        Token::LPar | Token::Number(_) | Token::Op(Operator::Sub) => {
-            let (val, input) = ll_parse_term(input)?;
-            ll_parse_addexpr(val.unwrap(), input)
+        Token::LPar
+        | Token::Number(_)
+        | Token::Ident(_)
+        | Token::Op(Operator::Sub)
+        | Token::Not
+        | Token::BitNot => {
+            let (ast, input) = ll_build_term(input)?;
+            ll_build_addexpr(ast, input)
         }
-        token => Err(MexeError::UnexpectedToken(token.to_string())),
+        _ => Err(unexpected(input)),
     }
 }
 
 // E' -> + T E'
 // E' -> - T E'
 // E' -> ε
-fn ll_parse_addexpr(val: f64, input: &[Token]) -> Result<(Option<f64>, &[Token])> {
-    match &input[0] {
-        t @ (Token::Op(Operator::Add) | Token::Op(Operator::Sub)) => {
-            let (val2, input) = ll_parse_term(&input[1..])?;
-
-            let val = match t {
-                Token::Op(Operator::Add) => val + val2.unwrap(),
-                Token::Op(Operator::Sub) => val - val2.unwrap(),
-                _ => unreachable!(),
-            };
-
-            ll_parse_addexpr(val, input)
+fn ll_build_addexpr<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(op @ (Operator::Add | Operator::Sub)) => {
+            let (rhs, input) = ll_build_term(&input[1..])?;
+            ll_build_addexpr(Ast::binary(op, lhs, rhs), input)
         }
-        _ => Ok((Some(val), input)),
+        _ => Ok((lhs, input)),
     }
 }
 
 // T  -> F T'
-fn ll_parse_term(input: &[Token]) -> Result<(Option<f64>, &[Token])> {
+fn ll_build_term<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
     match input[0] {
-        Token::LPar | Token::Number(_) | Token::Op(Operator::Sub) => {
-            let (val, input) = ll_parse_factor(input)?;
-
-            ll_parse_multerm(val.unwrap(), input)
+        Token::LPar
+        | Token::Number(_)
+        | Token::Ident(_)
+        | Token::Op(Operator::Sub)
+        | Token::Not
+        | Token::BitNot => {
+            let (ast, input) = ll_build_factor(input)?;
+            ll_build_multerm(ast, input)
         }
-        token => Err(MexeError::UnexpectedToken(token.to_string())),
+        _ => Err(unexpected(input)),
     }
 }
 
 // T' -> * F T'
 // T' -> / F T'
 // T' -> ε
-fn ll_parse_multerm(val: f64, input: &[Token]) -> Result<(Option<f64>, &[Token])> {
-    match &input[0] {
-        t @ (Token::Op(Operator::Mul) | Token::Op(Operator::Div)) => {
-            let (val2, input) = ll_parse_factor(&input[1..])?;
+fn ll_build_multerm<'a>(lhs: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match input[0] {
+        Token::Op(op @ (Operator::Mul | Operator::Div)) => {
+            let (rhs, input) = ll_build_factor(&input[1..])?;
+            ll_build_multerm(Ast::binary(op, lhs, rhs), input)
+        }
+        _ => Ok((lhs, input)),
+    }
+}
+
+// F  -> ( C )
+// F  -> n
+// F  -> v
+// F  -> - ( C )
+// F  -> - n
+// F  -> - v
+// F  -> ! F
+// F  -> ~ F
+// F  -> if ( C , C , C )
+fn ll_build_factor<'a>(input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    match (&input[0], input.get(1)) {
+        (Token::Op(Operator::Sub), Some(Token::LPar)) => {
+            let (ast, input) = ll_build_cond(&input[2..])?;
+            ll_build_rpar(Ast::Neg(Box::new(ast)), input)
+        }
+        (Token::Op(Operator::Sub), Some(Token::Number(n))) => Ok((Ast::Number(-*n), &input[2..])),
+        (Token::Op(Operator::Sub), Some(Token::Ident(name))) => {
+            Ok((Ast::Neg(Box::new(Ast::Var(name.to_string()))), &input[2..]))
+        }
+        (Token::Op(Operator::Sub), Some(Token::Not | Token::BitNot)) => {
+            let (ast, input) = ll_build_factor(&input[1..])?;
+            Ok((Ast::Neg(Box::new(ast)), input))
+        }
+        (Token::LPar, _) => {
+            let (ast, input) = ll_build_cond(&input[1..])?;
+            ll_build_rpar(ast, input)
+        }
+        (Token::Ident("if"), Some(Token::LPar)) => {
+            let (cond, input) = ll_build_cond(&input[2..])?;
+            let (then, input) = ll_build_cond(ll_build_expect(Token::Comma, input)?)?;
+            let (otherwise, input) = ll_build_cond(ll_build_expect(Token::Comma, input)?)?;
+            ll_build_rpar(Ast::cond(cond, then, otherwise), input)
+        }
+        (Token::Number(n), _) => Ok((Ast::Number(*n), &input[1..])),
+        (Token::Ident(name), _) => Ok((Ast::Var(name.to_string()), &input[1..])),
+        (Token::Not, _) => {
+            let (ast, input) = ll_build_factor(&input[1..])?;
+            Ok((Ast::Not(Box::new(ast)), input))
+        }
+        (Token::BitNot, _) => {
+            let (ast, input) = ll_build_factor(&input[1..])?;
+            Ok((Ast::BitNot(Box::new(ast)), input))
+        }
+        _ => Err(unexpected(input)),
+    }
+}
 
-            let val = match t {
-                Token::Op(Operator::Mul) => val * val2.unwrap(),
-                Token::Op(Operator::Div) => val / val2.unwrap(),
-                _ => unreachable!(),
-            };
+fn ll_build_expect<'a>(
+    token: Token,
+    input: &'a [Token<'a>],
+) -> Result<&'a [Token<'a>], Unexpected> {
+    if input[0] == token {
+        Ok(&input[1..])
+    } else {
+        Err(unexpected(input))
+    }
+}
+
+fn ll_build_rpar<'a>(ast: Ast, input: &'a [Token<'a>]) -> Parsed<'a, Ast> {
+    Ok((ast, ll_build_expect(Token::RPar, input)?))
+}
+
+// The `ll_eval_*` functions compute the value of the tokens as they parse
+// them, with the same grammar as the `ll_build_*` ones. The binary operators
+// are parsed by precedence climbing, with `Operator::precedence`, instead of
+// one function per level, so that each parenthesis only adds a few calls to
+// the stack, and the operands of a level are read in a loop. With `skip`, the
+// tokens are parsed but not evaluated, and the value returned is meaningless:
+// this is how the branch of a conditional that is not taken and the right
+// operand of a short-circuited `&&` or `||` are read. The first error found
+// while evaluating is stored in `failed`, and the rest of the tokens are then
+// read as with `skip`. The values passed between the functions are
+// `Operand`s, which do not carry errors.
+
+type Evaluated<'a> = Parsed<'a, Operand>;
 
-            ll_parse_multerm(val, input)
+type Unary = fn(Value) -> Result<Value, MexeError>;
+
+type Failed = Option<MexeError>;
+
+// A `Value` as the evaluation passes it around: a number, or a boolean if the
+// second field is set. Unlike a `Value`, it is moved in two registers instead
+// of through memory, which makes a large difference to the speed of `eval`.
+#[derive(Clone, Copy)]
+struct Operand(f64, Option<bool>);
+
+impl From<Value> for Operand {
+    fn from(val: Value) -> Operand {
+        match val {
+            Value::Number(n) => Operand(n, None),
+            Value::Bool(b) => Operand(0.0, Some(b)),
         }
-        _ => Ok((Some(val), input)),
     }
 }
 
-// F  -> ( E )
+impl From<Operand> for Value {
+    fn from(operand: Operand) -> Value {
+        match operand {
+            Operand(_, Some(b)) => Value::Bool(b),
+            Operand(n, None) => Value::Number(n),
+        }
+    }
+}
+
+// Below the precedence of all operators.
+const CONDITIONAL: u8 = 0;
+
+// C  -> O C'
+// C' -> ? C : C
+// C' -> ε
+//
+// when `min_precedence` is `CONDITIONAL`, and otherwise the operators from
+// `||` to `*` and `/` whose precedence is at least `min_precedence`, all
+// left-associative.
+fn ll_eval_binary<'a>(
+    input: &'a [Token<'a>],
+    min_precedence: u8,
+    skip: bool,
+    failed: &mut Failed,
+) -> Evaluated<'a> {
+    let (mut lhs, mut input) = ll_eval_factor(input, skip, failed)?;
+
+    loop {
+        match input[0] {
+            Token::Op(op) if op.precedence() >= min_precedence => {
+                (lhs, input) = ll_eval_operation(op, lhs, &input[1..], skip, failed)?;
+            }
+            Token::Question if min_precedence == CONDITIONAL => {
+                return ll_eval_branches(lhs, &input[1..], Token::Colon, skip, failed);
+            }
+            _ => return Ok((lhs, input)),
+        }
+    }
+}
+
+// Evaluates the branch that is taken and skips the other, which follows
+// `separator`.
+fn ll_eval_branches<'a>(
+    cond: Operand,
+    input: &'a [Token<'a>],
+    separator: Token,
+    skip: bool,
+    failed: &mut Failed,
+) -> Evaluated<'a> {
+    let skip = skip || failed.is_some();
+    let cond = match skip {
+        true => true,
+        false => eval::condition(cond.into()).unwrap_or_else(|err| {
+            *failed = Some(err);
+            true
+        }),
+    };
+
+    let (then, input) = ll_eval_binary(input, CONDITIONAL, skip || !cond, failed)?;
+    let input = ll_build_expect(separator, input)?;
+    let (otherwise, input) = ll_eval_binary(input, CONDITIONAL, skip || cond, failed)?;
+    Ok((if cond { then } else { otherwise }, input))
+}
+
+// The right operand of `op` is only evaluated if it is needed.
+fn ll_eval_operation<'a>(
+    op: Operator,
+    lhs: Operand,
+    input: &'a [Token<'a>],
+    skip: bool,
+    failed: &mut Failed,
+) -> Evaluated<'a> {
+    // only `&&` and `||` short-circuit
+    let logical = matches!(op, Operator::And | Operator::Or);
+    let short_circuit =
+        logical && !skip && failed.is_none() && eval::short_circuit(op, lhs.into()).is_some();
+    let (rhs, input) = ll_eval_binary(input, op.precedence() + 1, skip || short_circuit, failed)?;
+
+    // Arithmetic on numbers, the common case, does not need `eval::binary`.
+    let arithmetic = match (lhs, rhs) {
+        (Operand(lhs, None), Operand(rhs, None)) => op.apply(lhs, rhs),
+        _ => None,
+    };
+    let val = match (skip || failed.is_some(), arithmetic) {
+        (true, _) => lhs,
+        (false, Some(n)) => Operand(n, None),
+        (false, None) => {
+            let result = eval::short_circuit(op, lhs.into())
+                .unwrap_or_else(|| eval::binary(op, lhs.into(), rhs.into(), false));
+            ll_eval_step(result, lhs, failed)
+        }
+    };
+    Ok((val, input))
+}
+
+// F  -> ( C )
 // F  -> n
-// F  -> - ( E )
+// F  -> v
+// F  -> - ( C )
 // F  -> - n
-fn ll_parse_factor(input: &[Token]) -> Result<(Option<f64>, &[Token])> {
+// F  -> - v
+// F  -> ! F
+// F  -> ~ F
+// F  -> if ( C , C , C )
+//
+// Parentheses nest through this function and `ll_eval_parens`, so the other
+// rules are read by `ll_eval_operand`, and every rule by its own function: in
+// debug builds the stack frame of a function has room for the variables of all
+// its arms.
+fn ll_eval_factor<'a>(input: &'a [Token<'a>], skip: bool, failed: &mut Failed) -> Evaluated<'a> {
+    match input[0] {
+        Token::LPar => ll_eval_parens(&input[1..], skip, failed),
+        _ => ll_eval_operand(input, skip, failed),
+    }
+}
+
+fn ll_eval_operand<'a>(input: &'a [Token<'a>], skip: bool, failed: &mut Failed) -> Evaluated<'a> {
     match (&input[0], input.get(1)) {
-        (Token::Op(Operator::Sub), Some(Token::LPar)) => match ll_parse_expr(&input[2..]) {
-            Ok((Some(val), input)) => ll_consume_rpar(-val, input),
-            err => err,
-        },
-        (Token::Op(Operator::Sub), Some(Token::Number(n))) => Ok((Some(-*n), &input[2..])),
-        (Token::LPar, _) => match ll_parse_expr(&input[1..]) {
-            Ok((Some(val), input)) => ll_consume_rpar(val, input),
-            err => err,
-        },
-        (Token::Number(n), _) => Ok((Some(*n), &input[1..])),
-        (token, _) => Err(MexeError::UnexpectedToken(token.to_string())),
+        (Token::Op(Operator::Sub), Some(Token::Number(n))) => Ok((Operand(-*n, None), &input[2..])),
+        (Token::Op(Operator::Sub), Some(Token::Ident(name))) => {
+            Ok((ll_eval_var(eval::negate, name, skip, failed), &input[2..]))
+        }
+        (Token::Op(Operator::Sub), Some(Token::LPar | Token::Not | Token::BitNot)) => {
+            ll_eval_prefix(eval::negate, &input[1..], skip, failed)
+        }
+        (Token::Ident("if"), Some(Token::LPar)) => ll_eval_if(&input[2..], skip, failed),
+        (Token::Number(n), _) => Ok((Operand(*n, None), &input[1..])),
+        (Token::Ident(name), _) => Ok((ll_eval_var(Ok, name, skip, failed), &input[1..])),
+        (Token::Not, _) => ll_eval_prefix(eval::logical_not, &input[1..], skip, failed),
+        (Token::BitNot, _) => ll_eval_prefix(eval::bitwise_not, &input[1..], skip, failed),
+        _ => Err(unexpected(input)),
+    }
+}
+
+// The parenthesized expression that `input` starts with, after the `(`.
+fn ll_eval_parens<'a>(input: &'a [Token<'a>], skip: bool, failed: &mut Failed) -> Evaluated<'a> {
+    let (val, input) = ll_eval_binary(input, CONDITIONAL, skip, failed)?;
+    Ok((val, ll_build_expect(Token::RPar, input)?))
+}
+
+fn ll_eval_prefix<'a>(
+    op: Unary,
+    input: &'a [Token<'a>],
+    skip: bool,
+    failed: &mut Failed,
+) -> Evaluated<'a> {
+    let (val, input) = ll_eval_factor(input, skip, failed)?;
+    Ok((ll_eval_unary(op, val, skip, failed), input))
+}
+
+// The arguments of `if`, after the `(`.
+fn ll_eval_if<'a>(input: &'a [Token<'a>], skip: bool, failed: &mut Failed) -> Evaluated<'a> {
+    let (cond, input) = ll_eval_binary(input, CONDITIONAL, skip, failed)?;
+    let input = ll_build_expect(Token::Comma, input)?;
+    let (val, input) = ll_eval_branches(cond, input, Token::Comma, skip, failed)?;
+    Ok((val, ll_build_expect(Token::RPar, input)?))
+}
+
+// Only constants, such as `pi`, have a value without a `Context`.
+fn ll_eval_var(op: Unary, name: &str, skip: bool, failed: &mut Failed) -> Operand {
+    let val = Operand(0.0, None);
+
+    match (skip || failed.is_some(), context::constant(name)) {
+        (true, _) => val,
+        (false, Some(constant)) => ll_eval_step(op(Value::Number(constant)), val, failed),
+        (false, None) => {
+            let err = MexeError::UndefinedVariable(name.to_owned());
+            ll_eval_step(Err(err), val, failed)
+        }
     }
 }
 
-fn ll_consume_rpar(val: f64, input: &[Token]) -> Result<(Option<f64>, &[Token])> {
-    match input.get(0) {
-        Some(Token::RPar) => Ok((Some(val), &input[1..])),
-        None => Err(MexeError::UnexpectEndOfInput),
-        Some(t) => Err(MexeError::UnexpectedToken(t.to_string())),
+fn ll_eval_unary(op: Unary, val: Operand, skip: bool, failed: &mut Failed) -> Operand {
+    match skip || failed.is_some() {
+        true => val,
+        false => ll_eval_step(op(val.into()), val, failed),
     }
 }
+
+// The value of a step of the evaluation, or `val` if it failed, after storing
+// the error in `failed`.
+fn ll_eval_step(result: Result<Value, MexeError>, val: Operand, failed: &mut Failed) -> Operand {
+    result.map(Operand::from).unwrap_or_else(|err| {
+        *failed = Some(err);
+        val
+    })
+}
@@ -0,0 +1,265 @@
+use crate::{context, eval, lex, Ast, MexeError, Operator, Result, Syntax, Token, Value};
+
+/// A token of an expression in Reverse Polish Notation
+#[derive(Clone, Debug, PartialEq)]
+pub enum RpnToken {
+    /// A non-negative number
+    Number(f64),
+    /// A variable
+    Var(String),
+    /// Negation of the value on top of the stack, written as `neg`
+    Neg,
+    /// Logical negation of the value on top of the stack, written as `not`
+    Not,
+    /// Bitwise negation of the value on top of the stack, written as `~`
+    BitNot,
+    /// A conditional, written as `if`: the condition and the values if it is
+    /// true and if it is false are the three values on top of the stack
+    Cond,
+    /// A binary operation between the two values on top of the stack
+    Op(Operator),
+}
+
+impl std::fmt::Display for RpnToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            RpnToken::Number(n) => write!(f, "{}", n),
+            RpnToken::Var(name) => write!(f, "{}", name),
+            RpnToken::Neg => write!(f, "neg"),
+            RpnToken::Not => write!(f, "not"),
+            RpnToken::BitNot => write!(f, "~"),
+            RpnToken::Cond => write!(f, "if"),
+            RpnToken::Op(op) => write!(f, "{}", op),
+        }
+    }
+}
+
+/// Converts an infix expression to Reverse Polish Notation.
+///
+/// Negative numbers are emitted as the number followed by [`RpnToken::Neg`],
+/// so joining the tokens with spaces gives valid input for [`eval_rpn`].
+///
+/// `T`: type of the expression. Usually a `&str` or a `String`.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let rpn = mexe::to_rpn("(3 + 4) * -2")?;
+/// let rpn: Vec<String> = rpn.iter().map(|token| token.to_string()).collect();
+///
+/// assert_eq!(rpn.join(" "), "3 4 + 2 neg *");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// This function will return a [`MexeError`] if the input is not a valid
+/// arithmetic expression.
+pub fn to_rpn<T>(expression: T) -> Result<Vec<RpnToken>>
+where
+    T: AsRef<str>,
+{
+    let ast = crate::parse(expression)?;
+    let mut rpn = Vec::new();
+    push_rpn(&ast, &mut rpn);
+    Ok(rpn)
+}
+
+fn push_rpn(ast: &Ast, rpn: &mut Vec<RpnToken>) {
+    match ast {
+        Ast::Number(n) if n.is_sign_negative() => {
+            rpn.push(RpnToken::Number(-n));
+            rpn.push(RpnToken::Neg);
+        }
+        Ast::Number(n) => rpn.push(RpnToken::Number(*n)),
+        Ast::Var(name) => rpn.push(RpnToken::Var(name.clone())),
+        Ast::Neg(inner) => {
+            push_rpn(inner, rpn);
+            rpn.push(RpnToken::Neg);
+        }
+        Ast::Not(inner) => {
+            push_rpn(inner, rpn);
+            rpn.push(RpnToken::Not);
+        }
+        Ast::BitNot(inner) => {
+            push_rpn(inner, rpn);
+            rpn.push(RpnToken::BitNot);
+        }
+        Ast::Cond(cond, then, otherwise) => {
+            push_rpn(cond, rpn);
+            push_rpn(then, rpn);
+            push_rpn(otherwise, rpn);
+            rpn.push(RpnToken::Cond);
+        }
+        Ast::Binary(op, lhs, rhs) => {
+            push_rpn(lhs, rpn);
+            push_rpn(rhs, rpn);
+            rpn.push(RpnToken::Op(*op));
+        }
+    }
+}
+
+/// Evaluates an expression in Reverse Polish Notation, such as the output of
+/// [`to_rpn`].
+///
+/// Numbers are written as in [`eval`](crate::eval) and separated by
+/// whitespace. The binary operators apply to the two values on top of the
+/// stack, `neg`, `not` and `~` to the value on top of the stack, and `if` to
+/// the three values on top of the stack: the condition, and the values if it
+/// is true and if it is false. As in [`eval`](crate::eval), the only variable
+/// is `pi`, and the result must be a number. Unlike in infix expressions,
+/// all the operands are evaluated, including the branch of a conditional that
+/// is not taken and the right operand of `&&` and `||`.
+///
+/// `T`: type of the expression. Usually a `&str` or a `String`.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// assert_eq!(mexe::eval_rpn("3 4 + 2 *")?, 14.0);
+/// assert_eq!(mexe::eval_rpn("1 5 neg -")?, 6.0);
+/// assert_eq!(mexe::eval_rpn("1 4 << 3 |")?, 19.0);
+/// assert_eq!(mexe::eval_rpn("pi 3 < 1 2 if")?, 2.0);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// This function will return a [`MexeError`] if the input is not a valid
+/// RPN expression: [`MexeError::MissingOperand`] if an operator does not have
+/// enough values to apply to, and [`MexeError::MissingOperator`] if more than
+/// one value is left at the end. Values of the wrong type are reported as in
+/// [`eval`](crate::eval).
+pub fn eval_rpn<T>(expression: T) -> Result<f64>
+where
+    T: AsRef<str>,
+{
+    let expression = expression.as_ref();
+    let tokens = lex::get_tokens(expression)?;
+    let mut stack = Vec::with_capacity(tokens.len());
+    let pop = |stack: &mut Vec<Value>| stack.pop().ok_or(MexeError::MissingOperand);
+
+    for (index, token) in tokens.iter().enumerate() {
+        let val = match token {
+            Token::Number(n) => Value::Number(*n),
+            Token::Ident("neg") => eval::negate(pop(&mut stack)?)?,
+            Token::Ident("not") => eval::logical_not(pop(&mut stack)?)?,
+            Token::BitNot => eval::bitwise_not(pop(&mut stack)?)?,
+            Token::Ident("if") => {
+                let otherwise = pop(&mut stack)?;
+                let then = pop(&mut stack)?;
+
+                match eval::condition(pop(&mut stack)?)? {
+                    true => then,
+                    false => otherwise,
+                }
+            }
+            Token::Ident(name) => context::constant(name)
+                .map(Value::Number)
+                .ok_or_else(|| MexeError::UndefinedVariable(name.to_string()))?,
+            Token::Op(op) => {
+                let rhs = pop(&mut stack)?;
+                let lhs = pop(&mut stack)?;
+
+                eval::short_circuit(*op, lhs)
+                    .unwrap_or_else(|| eval::binary(*op, lhs, rhs, false))?
+            }
+            Token::EOI => break,
+            token => {
+                return Err(MexeError::UnexpectedToken(
+                    token.to_string(),
+                    lex::position(expression, &Syntax::default(), false, index),
+                ))
+            }
+        };
+
+        stack.push(val);
+    }
+
+    match stack[..] {
+        [val] => val.try_into(),
+        [] => Err(MexeError::MissingOperand),
+        _ => Err(MexeError::MissingOperator),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval;
+
+    #[test]
+    fn test_to_rpn() {
+        assert_eq!(
+            to_rpn("x - -1").unwrap(),
+            vec![
+                RpnToken::Var("x".to_owned()),
+                RpnToken::Number(1.0),
+                RpnToken::Neg,
+                RpnToken::Op(Operator::Sub),
+            ]
+        );
+        assert_eq!(
+            to_rpn("!(a < 1)").unwrap(),
+            vec![
+                RpnToken::Var("a".to_owned()),
+                RpnToken::Number(1.0),
+                RpnToken::Op(Operator::Lt),
+                RpnToken::Not,
+            ]
+        );
+        assert!(to_rpn("1 +").is_err());
+    }
+
+    #[test]
+    fn test_eval_rpn() {
+        assert_eq!(eval_rpn("2.5"), Ok(2.5));
+        assert_eq!(eval_rpn("10 2 3 - /"), Ok(-10.0));
+        assert_eq!(eval_rpn("2 neg neg"), Ok(2.0));
+        assert_eq!(eval_rpn("6 3 xor ~"), Ok(-6.0));
+        assert_eq!(eval_rpn(""), Err(MexeError::MissingOperand));
+        assert_eq!(eval_rpn("1 +"), Err(MexeError::MissingOperand));
+        assert_eq!(eval_rpn("neg"), Err(MexeError::MissingOperand));
+        assert_eq!(eval_rpn("1 2"), Err(MexeError::MissingOperator));
+        assert_eq!(
+            eval_rpn("1 ( 2 +"),
+            Err(MexeError::UnexpectedToken("(".to_owned(), 2))
+        );
+        assert_eq!(
+            eval_rpn("1 x +"),
+            Err(MexeError::UndefinedVariable("x".to_owned()))
+        );
+        assert_eq!(eval_rpn("1 2 <= not 3 4 if"), Ok(4.0));
+        assert_eq!(eval_rpn("1 2 < 1 1 == && 5 6 if"), Ok(5.0));
+        assert_eq!(
+            eval_rpn("1 2 <"),
+            Err(MexeError::TypeMismatch(
+                "expected a number, found a boolean".to_owned()
+            ))
+        );
+        assert_eq!(eval_rpn("1 2 if"), Err(MexeError::MissingOperand));
+    }
+
+    #[test]
+    fn rpn_output_evaluates_like_infix() {
+        let exprs = [
+            "1 + 2 * 3",
+            "(1 + 2) * 3",
+            "-(4 - 6) / -2",
+            "(7 * (((1.5 * (0.7 - 0.33 + (6.00 * 0.05))) / (2.5 + 3 * 0.1) + 6) * 3)) - 1.1",
+            "~(1 << 4 | 3) & 255 xor 7 >> 1",
+            "2 * pi",
+            "1 < 2 && !(3 >= 4) ? -1 : 2",
+            "if(pi != 3 || 1 == 2, 1 << 2, 0)",
+        ];
+
+        for expr in exprs.iter() {
+            let rpn: Vec<String> = to_rpn(expr)
+                .unwrap()
+                .iter()
+                .map(|t| t.to_string())
+                .collect();
+            assert_eq!(eval_rpn(rpn.join(" ")), eval(expr), "{}", expr);
+        }
+    }
+}
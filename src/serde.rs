@@ -0,0 +1,64 @@
+//! Helpers for using `mexe` with `serde`, available with the `serde` feature.
+
+use std::fmt;
+
+use ::serde::de::{self, Deserializer, Visitor};
+
+/// Deserializes an `f64` that can be written either as a number or as a
+/// string with an arithmetic expression, which is evaluated with
+/// [`eval`](crate::eval).
+///
+/// Meant to be used with `#[serde(deserialize_with = "mexe::serde::expr_f64")]`.
+/// If the expression is not valid, the [`MexeError`](crate::MexeError) is
+/// included in the message of the returned error. It requires a
+/// self-describing format, such as JSON or TOML.
+///
+/// ```
+/// # fn main() -> Result<(), serde_json::Error> {
+/// #[derive(serde::Deserialize)]
+/// struct Config {
+///     #[serde(deserialize_with = "mexe::serde::expr_f64")]
+///     ttl: f64,
+///     #[serde(deserialize_with = "mexe::serde::expr_f64")]
+///     ratio: f64,
+/// }
+///
+/// let config: Config = serde_json::from_str(r#"{"ttl": "60 * 60 * 24", "ratio": 0.5}"#)?;
+/// assert_eq!(config.ttl, 86400.0);
+/// assert_eq!(config.ratio, 0.5);
+/// # Ok(())
+/// # }
+/// ```
+pub fn expr_f64<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(ExprVisitor)
+}
+
+struct ExprVisitor;
+
+impl<'de> Visitor<'de> for ExprVisitor {
+    type Value = f64;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number or a string with an arithmetic expression")
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<f64, E> {
+        Ok(value)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<f64, E> {
+        Ok(value as f64)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<f64, E> {
+        Ok(value as f64)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<f64, E> {
+        crate::eval(value)
+            .map_err(|err| E::custom(format_args!("invalid expression `{}`: {}", value, err)))
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{lex, Ast, MexeError, Operator, Result, Syntax, Token};
+
+/// Converts an expression to an S-expression, such as `(+ 1 (* 2 3))`.
+///
+/// Chains of the same left-associative operator are written as a single list,
+/// `1 - 2 - 3` becoming `(- 1 2 3)`, unary minus is written as `(- x)`,
+/// logical negation as `(! x)` and conditionals as `(if c a b)`.
+/// The output can be read back into the same tree with [`parse_sexpr`].
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let ast = mexe::parse("1 + 2 * -x - 4")?;
+/// assert_eq!(mexe::to_sexpr(&ast), "(- (+ 1 (* 2 (- x))) 4)");
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_sexpr(ast: &Ast) -> String {
+    let mut sexpr = String::new();
+    write_sexpr(ast, &mut sexpr);
+    sexpr
+}
+
+fn write_sexpr(ast: &Ast, out: &mut String) {
+    match ast {
+        Ast::Number(n) => out.push_str(&n.to_string()),
+        Ast::Var(name) => out.push_str(name),
+        Ast::Neg(inner) => {
+            out.push_str("(- ");
+            write_sexpr(inner, out);
+            out.push(')');
+        }
+        Ast::Not(inner) => {
+            out.push_str("(! ");
+            write_sexpr(inner, out);
+            out.push(')');
+        }
+        Ast::BitNot(inner) => {
+            out.push_str("(~ ");
+            write_sexpr(inner, out);
+            out.push(')');
+        }
+        Ast::Cond(cond, then, otherwise) => {
+            out.push_str("(if ");
+            write_sexpr(cond, out);
+            out.push(' ');
+            write_sexpr(then, out);
+            out.push(' ');
+            write_sexpr(otherwise, out);
+            out.push(')');
+        }
+        Ast::Binary(op, _, _) => {
+            // collect the left-nested operands that share the same operator
+            let mut operands = Vec::new();
+            let mut first = ast;
+
+            while let Ast::Binary(o, lhs, rhs) = first {
+                if o != op {
+                    break;
+                }
+
+                operands.push(rhs.as_ref());
+                first = lhs;
+            }
+
+            out.push('(');
+            out.push_str(&op.to_string());
+            out.push(' ');
+            write_sexpr(first, out);
+
+            for operand in operands.iter().rev() {
+                out.push(' ');
+                write_sexpr(operand, out);
+            }
+
+            out.push(')');
+        }
+    }
+}
+
+/// Parses an S-expression into an [`Ast`].
+///
+/// A list starts with an operator, which is applied from left to right to
+/// two or more operands: `(- 10 2 3)` is the same as `10 - 2 - 3`. A minus
+/// with a single operand, as in `(- x)`, is a negation, `!` takes exactly
+/// one operand, as in `(! (< x 1))`, and `if` takes exactly three: the
+/// condition and the values if it is true and if it is false. Numbers and
+/// variables are written as in [`parse`](crate::parse), and a number can be
+/// preceded by `-`.
+///
+/// `T`: type of the expression. Usually a `&str` or a `String`.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let ast = mexe::parse_sexpr("(* (+ x 1) (- y) -2)")?;
+/// assert_eq!(ast, mexe::parse("(x + 1) * -y * -2")?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// This function will return a [`MexeError`] if the input is not a valid
+/// S-expression: [`MexeError::MissingOperator`] if a list does not start with
+/// an operator, and [`MexeError::MissingOperand`] if it does not have enough
+/// operands.
+pub fn parse_sexpr<T>(expression: T) -> Result<Ast>
+where
+    T: AsRef<str>,
+{
+    let expression = expression.as_ref();
+    let tokens = lex::get_tokens(expression)?;
+
+    let parsed = match sexpr_parse_node(&tokens[..]) {
+        Ok((ast, [Token::EOI])) => Ok(ast),
+        Ok((_, input)) => Err(unexpected(input)),
+        Err(err) => Err(err),
+    };
+
+    // the parsing functions only see the rest of the input, so an unexpected
+    // token is reported with the number of tokens left from it
+    parsed.map_err(|err| match err {
+        MexeError::UnexpectedToken(token, left) => MexeError::UnexpectedToken(
+            token,
+            lex::position(expression, &Syntax::default(), false, tokens.len() - left),
+        ),
+        err => err,
+    })
+}
+
+fn sexpr_parse_node<'a>(input: &'a [Token<'a>]) -> Result<(Ast, &'a [Token<'a>])> {
+    match (&input[0], input.get(1)) {
+        (Token::Number(n), _) => Ok((Ast::Number(*n), &input[1..])),
+        (Token::Op(Operator::Sub), Some(Token::Number(n))) => Ok((Ast::Number(-*n), &input[2..])),
+        (Token::Ident(name), _) => Ok((Ast::Var(name.to_string()), &input[1..])),
+        (Token::LPar, _) => sexpr_parse_list(&input[1..]),
+        (Token::EOI, _) => Err(MexeError::UnexpectEndOfInput),
+        _ => Err(unexpected(input)),
+    }
+}
+
+fn sexpr_parse_list<'a>(input: &'a [Token<'a>]) -> Result<(Ast, &'a [Token<'a>])> {
+    let op = match input[0] {
+        Token::Op(op) => op,
+        Token::Not => {
+            let (ast, input) = sexpr_parse_operand(&input[1..])?;
+            return sexpr_parse_rpar(Ast::Not(Box::new(ast)), input);
+        }
+        Token::BitNot => {
+            let (ast, input) = sexpr_parse_operand(&input[1..])?;
+            return sexpr_parse_rpar(Ast::BitNot(Box::new(ast)), input);
+        }
+        Token::Ident("if") => {
+            let (cond, input) = sexpr_parse_operand(&input[1..])?;
+            let (then, input) = sexpr_parse_operand(input)?;
+            let (otherwise, input) = sexpr_parse_operand(input)?;
+            return sexpr_parse_rpar(Ast::cond(cond, then, otherwise), input);
+        }
+        Token::EOI => return Err(MexeError::UnexpectEndOfInput),
+        _ => return Err(MexeError::MissingOperator),
+    };
+
+    if input[1] == Token::RPar {
+        return Err(MexeError::MissingOperand);
+    }
+
+    let (first, mut input) = sexpr_parse_node(&input[1..])?;
+
+    if input[0] == Token::RPar {
+        return match op {
+            Operator::Sub => Ok((Ast::neg(first), &input[1..])),
+            _ => Err(MexeError::MissingOperand),
+        };
+    }
+
+    let mut ast = first;
+
+    while input[0] != Token::RPar {
+        let (rhs, rest) = sexpr_parse_node(input)?;
+        ast = Ast::binary(op, ast, rhs);
+        input = rest;
+    }
+
+    Ok((ast, &input[1..]))
+}
+
+// An operand of a list that takes a fixed number of them.
+fn sexpr_parse_operand<'a>(input: &'a [Token<'a>]) -> Result<(Ast, &'a [Token<'a>])> {
+    match input[0] {
+        Token::RPar => Err(MexeError::MissingOperand),
+        _ => sexpr_parse_node(input),
+    }
+}
+
+fn sexpr_parse_rpar<'a>(ast: Ast, input: &'a [Token<'a>]) -> Result<(Ast, &'a [Token<'a>])> {
+    match input[0] {
+        Token::RPar => Ok((ast, &input[1..])),
+        Token::EOI => Err(MexeError::UnexpectEndOfInput),
+        _ => Err(unexpected(input)),
+    }
+}
+
+fn unexpected(input: &[Token]) -> MexeError {
+    MexeError::UnexpectedToken(input[0].to_string(), input.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_to_sexpr() {
+        let exprs = [
+            ("1", "1"),
+            ("-1", "-1"),
+            ("-x", "(- x)"),
+            ("-(1)", "(- 1)"),
+            ("1 + 2 + 3", "(+ 1 2 3)"),
+            ("1 + (2 + 3)", "(+ 1 (+ 2 3))"),
+            ("1 - 2 + 3", "(+ (- 1 2) 3)"),
+            ("a / b / (c * d)", "(/ a b (* c d))"),
+            ("!(a < 1) || b && c", "(|| (! (< a 1)) (&& b c))"),
+            ("a ? b : c ? 1 : 2", "(if a b (if c 1 2))"),
+            ("~a & b xor c << 1", "(xor (& (~ a) b) (<< c 1))"),
+        ];
+
+        for (expr, sexpr) in exprs.iter() {
+            assert_eq!(&to_sexpr(&parse(expr).unwrap()), sexpr);
+        }
+    }
+
+    #[test]
+    fn test_parse_sexpr() {
+        assert_eq!(parse_sexpr("x"), parse("x"));
+        assert_eq!(parse_sexpr("(- 5 2 1)"), parse("5 - 2 - 1"));
+        assert_eq!(parse_sexpr("(- (+ 1 2))"), parse("-(1 + 2)"));
+        assert_eq!(parse_sexpr("(/ -1 (- y))"), parse("-1 / -y"));
+        assert_eq!(parse_sexpr("(! (== a b 1))"), parse("!(a == b == 1)"));
+        assert_eq!(
+            parse_sexpr("(! a b)"),
+            Err(MexeError::UnexpectedToken("b".to_owned(), 5))
+        );
+        assert_eq!(
+            parse_sexpr("(if (> x 0) x (- x))"),
+            parse("if(x > 0, x, -x)")
+        );
+        assert_eq!(parse_sexpr("(if a b)"), Err(MexeError::MissingOperand));
+        assert_eq!(parse_sexpr("(+ 1)"), Err(MexeError::MissingOperand));
+        assert_eq!(parse_sexpr("(-)"), Err(MexeError::MissingOperand));
+        assert_eq!(parse_sexpr("(1 2)"), Err(MexeError::MissingOperator));
+        assert_eq!(parse_sexpr("()"), Err(MexeError::MissingOperator));
+        assert_eq!(parse_sexpr("(+ 1 2"), Err(MexeError::UnexpectEndOfInput));
+        assert_eq!(
+            parse_sexpr("(+ 1 2))"),
+            Err(MexeError::UnexpectedToken(")".to_owned(), 7))
+        );
+    }
+
+    #[test]
+    fn sexpr_round_trip() {
+        let exprs = [
+            "-(x - (y - -(z / (w / v))))",
+            "(1 + (4 * 5)) / 2 - 3 * 0.7",
+            "a * b * c - (a - b - c) / -(-2)",
+            "!(x >= 1 && !y) != (z <= 2 || w)",
+            "(a ? b : c) ? -(d ? 1 : 2) : e",
+            "~(a | b xor c) >> (1 & ~d)",
+        ];
+
+        for expr in exprs.iter() {
+            let ast = parse(expr).unwrap();
+            assert_eq!(parse_sexpr(to_sexpr(&ast)).unwrap(), ast, "{}", expr);
+        }
+    }
+}
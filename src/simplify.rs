@@ -0,0 +1,621 @@
+use std::cmp::Ordering;
+
+use crate::{eval, Ast, Operator, Value};
+
+/// Simplifies an expression.
+///
+/// Constant subexpressions are folded, neutral elements (`x + 0`, `x * 1`)
+/// and double negations are removed, like terms are collected (`x + 2 * x`
+/// becomes `3 * x`) and the operands of sums and products are sorted in a
+/// canonical order, so equivalent expressions such as `b + a` and `a + b`
+/// simplify to the same [`Ast`]. A conditional whose condition is constant is
+/// replaced by the branch it takes. Parentheses are not part of the tree, so
+/// printing the result only emits the ones that are needed.
+///
+/// The rules assume real arithmetic: operands are reordered, `0 * x` is
+/// folded to `0` and `x / x` to `1`. Constants are only folded when the
+/// result is finite, and operations that fail to evaluate, such as `1 << 64`
+/// or `!0`, are left as they are.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// let ast = mexe::parse("(y * 2 + 0) - -(1 * x) + 3 * (4 - 2) + y")?;
+/// assert_eq!(mexe::simplify(&ast).to_string(), "x + 3 * y + 6");
+/// # Ok(())
+/// # }
+/// ```
+pub fn simplify(ast: &Ast) -> Ast {
+    let mut ast = to_ast(&to_sum(ast));
+
+    // the normal form is reached in one pass, unless folding a constant
+    // overflowed, which leaves that operation unfolded
+    loop {
+        let next = to_ast(&to_sum(&ast));
+
+        if next == ast {
+            return ast;
+        }
+
+        ast = next;
+    }
+}
+
+// A constant kept as a fraction, so that dividing by a constant stays exact,
+// as in `x / 3 * 3`. The denominator is positive, and it is 1 if the quotient
+// is exact, such as for `x / 4`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Ratio {
+    num: f64,
+    den: f64,
+}
+
+impl Ratio {
+    const ZERO: Ratio = Ratio { num: 0.0, den: 1.0 };
+    const ONE: Ratio = Ratio { num: 1.0, den: 1.0 };
+
+    // Returns `None` if the value is not finite.
+    fn new(num: f64, den: f64) -> Option<Ratio> {
+        let (num, den) = if den < 0.0 { (-num, -den) } else { (num, den) };
+
+        if !(num / den).is_finite() || !den.is_finite() {
+            return None;
+        } else if num == 0.0 {
+            return Some(Ratio::ZERO);
+        }
+
+        let (num, den) = match integers(num, den) {
+            Some((num, den)) => {
+                let divisor = gcd(num.abs(), den);
+                (num / divisor, den / divisor)
+            }
+            None => (num, den),
+        };
+
+        // dividing by a power of two is exact
+        if den.to_bits() & ((1 << 52) - 1) == 0 {
+            Some(Ratio {
+                num: num / den,
+                den: 1.0,
+            })
+        } else {
+            Some(Ratio { num, den })
+        }
+    }
+
+    fn add(self, other: Ratio) -> Option<Ratio> {
+        Ratio::new(
+            self.num * other.den + other.num * self.den,
+            self.den * other.den,
+        )
+    }
+
+    fn mul(self, other: Ratio) -> Option<Ratio> {
+        Ratio::new(self.num * other.num, self.den * other.den)
+    }
+
+    fn div(self, other: Ratio) -> Option<Ratio> {
+        Ratio::new(self.num * other.den, self.den * other.num)
+    }
+
+    fn neg(self) -> Ratio {
+        match self {
+            Ratio::ZERO => Ratio::ZERO,
+            Ratio { num, den } => Ratio { num: -num, den },
+        }
+    }
+
+    // The fraction with an integer numerator and denominator, if any.
+    fn integers(self) -> Option<(f64, f64)> {
+        integers(self.num, self.den)
+    }
+
+    fn to_ast(self) -> Ast {
+        if self.den == 1.0 {
+            Ast::Number(self.num)
+        } else {
+            Ast::binary(Operator::Div, Ast::Number(self.num), Ast::Number(self.den))
+        }
+    }
+}
+
+// Short binary fractions, such as `4.5`, become integers when doubled a few
+// times. Others, such as `0.1`, are not treated as fractions.
+fn integers(num: f64, den: f64) -> Option<(f64, f64)> {
+    let (mut num, mut den) = (num, den);
+
+    for _ in 0..MAX_DOUBLINGS {
+        if is_integer(num) && is_integer(den) {
+            return Some((num, den));
+        }
+
+        num *= 2.0;
+        den *= 2.0;
+    }
+
+    None
+}
+
+const MAX_DOUBLINGS: u32 = 16;
+
+// 2^53, above which not every integer is an `f64`
+const MAX_INTEGER: f64 = 9007199254740992.0;
+
+fn is_integer(n: f64) -> bool {
+    n.fract() == 0.0 && n.abs() < MAX_INTEGER
+}
+
+fn gcd(a: f64, b: f64) -> f64 {
+    if b == 0.0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// The normal form of arithmetic: a sum of terms and a constant, where each
+// term is a coefficient and a product, and no two terms have the same
+// product. The terms are sorted by their products.
+#[derive(Clone, Debug, PartialEq)]
+struct Sum {
+    terms: Vec<(Ratio, Product)>,
+    constant: Ratio,
+}
+
+// The factors of the numerator and of the denominator of a term, each sorted.
+// A factor is a sum of more than one part, or a node that is not arithmetic,
+// such as a variable or a comparison.
+#[derive(Clone, Debug, PartialEq)]
+struct Product {
+    num: Vec<Ast>,
+    den: Vec<Ast>,
+}
+
+impl Sum {
+    fn constant(constant: Ratio) -> Sum {
+        Sum {
+            terms: Vec::new(),
+            constant,
+        }
+    }
+
+    fn term(coef: Ratio, product: Product) -> Sum {
+        match coef {
+            Ratio::ZERO => Sum::constant(Ratio::ZERO),
+            coef => Sum {
+                terms: vec![(coef, product)],
+                constant: Ratio::ZERO,
+            },
+        }
+    }
+
+    // A product whose only factor is a sum is that sum, as in `3 / (1 / (x + 1))`,
+    // and a product without factors is a constant.
+    fn product(coef: Ratio, product: Product) -> Option<Sum> {
+        match (&product.num[..], &product.den[..]) {
+            ([], []) => Some(Sum::constant(coef)),
+            ([sum @ Ast::Binary(Operator::Add | Operator::Sub, ..)], []) => to_sum(sum).scale(coef),
+            _ => Some(Sum::term(coef, product)),
+        }
+    }
+
+    fn factor(ast: Ast) -> Sum {
+        Sum::term(
+            Ratio::ONE,
+            Product {
+                num: vec![ast],
+                den: Vec::new(),
+            },
+        )
+    }
+
+    fn as_constant(&self) -> Option<Ratio> {
+        match self.terms.is_empty() {
+            true => Some(self.constant),
+            false => None,
+        }
+    }
+
+    // The sum as a single term. A sum of more than one part becomes a factor,
+    // divided by the sign of its first term and by the greatest common
+    // divisor of its coefficients, so that `2 * x + 2` and `-x - 1` are both
+    // a multiple of `x + 1`.
+    fn as_term(&self) -> Option<(Ratio, Product)> {
+        if self.constant == Ratio::ZERO && self.terms.len() == 1 {
+            return Some(self.terms[0].clone());
+        }
+
+        let coefs = self.terms.iter().map(|(coef, _)| *coef);
+        let fractions = coefs.chain(Some(self.constant)).map(Ratio::integers);
+        let sign = self.terms[0].0.num.signum();
+
+        let scale = match fractions.collect::<Option<Vec<_>>>() {
+            Some(fractions) => {
+                let num = fractions.iter().fold(0.0, |acc, (n, _)| gcd(acc, n.abs()));
+                let den = fractions
+                    .iter()
+                    .fold(1.0, |acc, (_, d)| acc * d / gcd(acc, *d));
+                Ratio::new(sign * num, den)?
+            }
+            None => Ratio::new(sign, 1.0)?,
+        };
+
+        let factor = self.scale(Ratio::ONE.div(scale)?)?;
+        let product = Product {
+            num: vec![to_ast(&factor)],
+            den: Vec::new(),
+        };
+        Some((scale, product))
+    }
+
+    fn neg(&self) -> Sum {
+        Sum {
+            terms: self
+                .terms
+                .iter()
+                .map(|(coef, product)| (coef.neg(), product.clone()))
+                .collect(),
+            constant: self.constant.neg(),
+        }
+    }
+
+    fn scale(&self, factor: Ratio) -> Option<Sum> {
+        let mut sum = Sum::constant(self.constant.mul(factor)?);
+
+        for (coef, product) in self.terms.iter() {
+            sum = sum.add(&Sum::term(coef.mul(factor)?, product.clone()))?;
+        }
+
+        Some(sum)
+    }
+
+    fn add(&self, other: &Sum) -> Option<Sum> {
+        let mut sum = self.clone();
+        sum.constant = sum.constant.add(other.constant)?;
+
+        for (coef, product) in other.terms.iter() {
+            match sum.terms.iter_mut().find(|(_, p)| p == product) {
+                Some((c, _)) => *c = c.add(*coef)?,
+                None => sum.terms.push((*coef, product.clone())),
+            }
+        }
+
+        sum.terms.retain(|(coef, _)| *coef != Ratio::ZERO);
+        sum.terms.sort_by(|(_, a), (_, b)| cmp_product(a, b));
+        Some(sum)
+    }
+
+    fn mul(&self, other: &Sum) -> Option<Sum> {
+        match (self.as_constant(), other.as_constant()) {
+            (Some(c), _) => other.scale(c),
+            (_, Some(c)) => self.scale(c),
+            _ => {
+                let (lhs_coef, lhs) = self.as_term()?;
+                let (rhs_coef, rhs) = other.as_term()?;
+                Sum::product(lhs_coef.mul(rhs_coef)?, lhs.mul(&rhs))
+            }
+        }
+    }
+
+    // `None` for a division by zero, which is not folded.
+    fn div(&self, other: &Sum) -> Option<Sum> {
+        if let Some(c) = other.as_constant() {
+            return self.scale(Ratio::ONE.div(c)?);
+        }
+
+        let (rhs_coef, rhs) = other.as_term()?;
+        let (coef, inverse) = (Ratio::ONE.div(rhs_coef)?, rhs.invert());
+
+        match self.as_constant() {
+            Some(c) => Sum::product(c.mul(coef)?, inverse),
+            None => {
+                let (lhs_coef, lhs) = self.as_term()?;
+                Sum::product(lhs_coef.mul(coef)?, lhs.mul(&inverse))
+            }
+        }
+    }
+}
+
+impl Product {
+    // A factor of both the numerator and the denominator cancels out, as in
+    // `x * y / x`.
+    fn mul(&self, other: &Product) -> Product {
+        let mut num: Vec<_> = self.num.iter().chain(other.num.iter()).cloned().collect();
+        let mut den = Vec::new();
+
+        for factor in self.den.iter().chain(other.den.iter()) {
+            match num.iter().position(|f| f == factor) {
+                Some(i) => {
+                    num.remove(i);
+                }
+                None => den.push(factor.clone()),
+            }
+        }
+
+        num.sort_by(cmp_ast);
+        den.sort_by(cmp_ast);
+        Product { num, den }
+    }
+
+    fn invert(self) -> Product {
+        Product {
+            num: self.den,
+            den: self.num,
+        }
+    }
+}
+
+// Folds and collects the arithmetic of a node. The other nodes become factors,
+// with their operands simplified.
+fn to_sum(ast: &Ast) -> Sum {
+    match ast {
+        Ast::Number(n) => match Ratio::new(*n, 1.0) {
+            Some(n) => Sum::constant(n),
+            None => Sum::factor(ast.clone()),
+        },
+        Ast::Var(_) => Sum::factor(ast.clone()),
+        Ast::Neg(inner) => to_sum(inner).neg(),
+        Ast::Not(inner) => fold(Ast::Not(Box::new(simplify(inner)))),
+        Ast::BitNot(inner) => fold(Ast::BitNot(Box::new(simplify(inner)))),
+        Ast::Cond(cond, then, otherwise) => {
+            let cond = simplify(cond);
+
+            match constant(&cond).map(eval::condition) {
+                Some(Ok(true)) => to_sum(then),
+                Some(Ok(false)) => to_sum(otherwise),
+                _ => Sum::factor(Ast::cond(cond, simplify(then), simplify(otherwise))),
+            }
+        }
+        Ast::Binary(
+            op @ (Operator::Add | Operator::Sub | Operator::Mul | Operator::Div),
+            lhs,
+            rhs,
+        ) => {
+            let (lhs, rhs) = (to_sum(lhs), to_sum(rhs));
+            let sum = match op {
+                Operator::Add => lhs.add(&rhs),
+                Operator::Sub => lhs.add(&rhs.neg()),
+                Operator::Mul => lhs.mul(&rhs),
+                _ => lhs.div(&rhs),
+            };
+
+            // an overflow or a division by zero is left as it is
+            sum.unwrap_or_else(|| Sum::factor(Ast::binary(*op, to_ast(&lhs), to_ast(&rhs))))
+        }
+        Ast::Binary(op, lhs, rhs) => fold(Ast::binary(*op, simplify(lhs), simplify(rhs))),
+    }
+}
+
+// A node whose operands are simplified, as a constant if its value is a finite
+// number, and otherwise as a factor.
+fn fold(ast: Ast) -> Sum {
+    match constant(&ast) {
+        Some(Value::Number(n)) if n.is_finite() => to_sum(&Ast::Number(n)),
+        _ => Sum::factor(ast),
+    }
+}
+
+// The value of a node without variables, as `eval_strict` computes it, or
+// `None` if the node has variables or evaluating it fails.
+fn constant(ast: &Ast) -> Option<Value> {
+    match ast {
+        Ast::Number(n) => Some(Value::Number(*n)),
+        Ast::Var(_) => None,
+        Ast::Neg(inner) => eval::negate(constant(inner)?).ok(),
+        Ast::Not(inner) => eval::logical_not(constant(inner)?).ok(),
+        Ast::BitNot(inner) => eval::bitwise_not(constant(inner)?).ok(),
+        Ast::Cond(cond, then, otherwise) => match eval::condition(constant(cond)?).ok()? {
+            true => constant(then),
+            false => constant(otherwise),
+        },
+        Ast::Binary(op, lhs, rhs) => {
+            let lhs = constant(lhs)?;
+
+            match eval::short_circuit(*op, lhs) {
+                Some(result) => result.ok(),
+                None => eval::binary(*op, lhs, constant(rhs)?, true).ok(),
+            }
+        }
+    }
+}
+
+fn to_ast(sum: &Sum) -> Ast {
+    let parts = sum
+        .terms
+        .iter()
+        .map(|(coef, product)| (*coef, Some(product)));
+    let constant = match sum.constant {
+        Ratio::ZERO if !sum.terms.is_empty() => None,
+        constant => Some((constant, None)),
+    };
+
+    parts
+        .chain(constant)
+        .fold(None, |ast, (coef, product)| {
+            let part = |coef| match product {
+                Some(product) => term_ast(coef, product),
+                None => Ratio::to_ast(coef),
+            };
+
+            Some(match ast {
+                None => part(coef),
+                Some(ast) if coef.num < 0.0 => Ast::binary(Operator::Sub, ast, part(coef.neg())),
+                Some(ast) => Ast::binary(Operator::Add, ast, part(coef)),
+            })
+        })
+        .unwrap() // infallible, there is at least one part
+}
+
+// The coefficient goes in front of the leftmost factor, so that the product
+// stays left-associative and prints without parentheses.
+fn term_ast(coef: Ratio, product: &Product) -> Ast {
+    let num = match product.num.split_first() {
+        None => Ast::Number(coef.num),
+        Some((first, rest)) => {
+            let first = if coef.num == 1.0 {
+                first.clone()
+            } else if coef.num == -1.0 {
+                Ast::neg(first.clone())
+            } else {
+                Ast::binary(Operator::Mul, Ast::Number(coef.num), first.clone())
+            };
+
+            rest.iter().fold(first, |lhs, rhs| {
+                Ast::binary(Operator::Mul, lhs, rhs.clone())
+            })
+        }
+    };
+
+    let den = (coef.den != 1.0).then_some(Ast::Number(coef.den));
+    let den = den
+        .into_iter()
+        .chain(product.den.iter().cloned())
+        .reduce(|lhs, rhs| Ast::binary(Operator::Mul, lhs, rhs));
+
+    match den {
+        Some(den) => Ast::binary(Operator::Div, num, den),
+        None => num,
+    }
+}
+
+fn cmp_product(a: &Product, b: &Product) -> Ordering {
+    let cmp = |a: &[Ast], b: &[Ast]| {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| cmp_ast(a, b))
+            .find(|ord| ord.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len()))
+    };
+
+    cmp(&a.num, &b.num).then_with(|| cmp(&a.den, &b.den))
+}
+
+fn cmp_ast(a: &Ast, b: &Ast) -> Ordering {
+    fn rank(ast: &Ast) -> u8 {
+        match ast {
+            Ast::Number(_) => 0,
+            Ast::Var(_) => 1,
+            Ast::Neg(_) => 2,
+            Ast::Not(_) => 3,
+            Ast::BitNot(_) => 4,
+            Ast::Binary(..) => 5,
+            Ast::Cond(..) => 6,
+        }
+    }
+
+    match (a, b) {
+        (Ast::Number(a), Ast::Number(b)) => a.total_cmp(b),
+        (Ast::Var(a), Ast::Var(b)) => a.cmp(b),
+        (Ast::Neg(a), Ast::Neg(b))
+        | (Ast::Not(a), Ast::Not(b))
+        | (Ast::BitNot(a), Ast::BitNot(b)) => cmp_ast(a, b),
+        (Ast::Binary(op_a, lhs_a, rhs_a), Ast::Binary(op_b, lhs_b, rhs_b)) => (*op_a as u8)
+            .cmp(&(*op_b as u8))
+            .then_with(|| cmp_ast(lhs_a, lhs_b))
+            .then_with(|| cmp_ast(rhs_a, rhs_b)),
+        (Ast::Cond(cond_a, then_a, else_a), Ast::Cond(cond_b, then_b, else_b)) => {
+            cmp_ast(cond_a, cond_b)
+                .then_with(|| cmp_ast(then_a, then_b))
+                .then_with(|| cmp_ast(else_a, else_b))
+        }
+        (a, b) => rank(a).cmp(&rank(b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn s(expr: &str) -> String {
+        simplify(&parse(expr).unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_simplify() {
+        assert_eq!(s("1 + 2 * 3"), "7");
+        assert_eq!(s("x + 0"), "x");
+        assert_eq!(s("0 - x"), "-x");
+        assert_eq!(s("1 * x / 1"), "x");
+        assert_eq!(s("-(-x)"), "x");
+        assert_eq!(s("-(-(x + 1))"), "x + 1");
+        assert_eq!(s("0 * (x + y)"), "0");
+        assert_eq!(s("x - x"), "0");
+        assert_eq!(s("x + 2 * x"), "3 * x");
+        assert_eq!(s("y * x - 2 * x * y"), "-x * y");
+        assert_eq!(s("-(x - y)"), "-x + y");
+        assert_eq!(s("3 / x + 6 / x"), "9 / x");
+        assert_eq!(s("2 * x / y - x / y"), "x / y");
+        assert_eq!(s("1 / 0"), "1 / 0");
+        assert_eq!(s("2 * x / (4 * x)"), "0.5");
+        assert_eq!(s("x * y / (y * (x + 1))"), "x / (x + 1)");
+        assert_eq!(s("(2 * x + 2) / (x + 1)"), "2");
+        assert_eq!(s("(0.1 - z) * (z + 3)"), "-(z + 3) * (z - 0.1)");
+        assert_eq!(s("(x + (y * (z)))"), "x + y * z");
+        assert_eq!(s("x + 0 < 2 * 3 && !(1 * y == y)"), "x < 6 && !(y == y)");
+        assert_eq!(s("1 < 2"), "1 < 2");
+        assert_eq!(s("x > 0 ? x * 1 : 2 * 3 - x"), "x > 0 ? x : -x + 6");
+    }
+
+    #[test]
+    fn constant_operations_are_folded() {
+        assert_eq!(s("1 << 4"), "16");
+        assert_eq!(s("x * (6 & 3 | 8)"), "10 * x");
+        assert_eq!(s("(7 >> 1) - x"), "-x + 3");
+        assert_eq!(s("~3"), "-4");
+        assert_eq!(s("~(1 + 2) * x"), "-4 * x");
+        assert_eq!(s("~~x"), "~~x");
+
+        // booleans have no literal, and errors are left to evaluation
+        assert_eq!(s("1 + 1 == 2"), "2 == 2");
+        assert_eq!(s("!0"), "!0");
+        assert_eq!(s("!(0 < 1)"), "!(0 < 1)");
+        assert_eq!(s("1 << 64"), "1 << 64");
+        assert_eq!(s("~0.5"), "~0.5");
+    }
+
+    #[test]
+    fn constant_conditions_take_their_branch() {
+        assert_eq!(s("1 < 2 ? x : y"), "x");
+        assert_eq!(s("1 > 2 ? x : y * 2"), "2 * y");
+        assert_eq!(s("if(!(1 < 2), x, y)"), "y");
+        assert_eq!(s("2 > 1 || x ? x + 0 : y"), "x");
+        assert_eq!(s("1 > 2 && x ? x : 1 << 4"), "16");
+        assert_eq!(s("(1 < 2 ? 3 : 4) + x"), "x + 3");
+        assert_eq!(s("x < 1 ? 2 : 3"), "x < 1 ? 2 : 3");
+
+        // `eval_strict` rejects the division, so the condition is kept
+        assert_eq!(s("1 / 0 > 0 ? x : y"), "1 / 0 > 0 ? x : y");
+        assert_eq!(s("1 ? x : y"), "1 ? x : y");
+    }
+
+    #[test]
+    fn equivalent_expressions_simplify_equally() {
+        let pairs = [
+            ("a + b", "b + a"),
+            ("a * b * c", "c * (b * a)"),
+            ("2 * a - b + 1", "1 - (b - a * 2)"),
+            ("x / y * 2", "2 * x / y"),
+            ("-(a * b)", "a * -b"),
+        ];
+
+        for (a, b) in pairs.iter() {
+            assert_eq!(s(a), s(b), "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn simplify_is_idempotent() {
+        let exprs = [
+            "a + b * 2 - 3 * (c - a)",
+            "-(x / y) + 3 / y - x * -2",
+            "x * (y / z) * 4",
+            "(x + 1) * (x - 1) / -(2 * x)",
+        ];
+
+        for expr in exprs.iter() {
+            let once = simplify(&parse(expr).unwrap());
+            assert_eq!(simplify(&once), once, "{}", expr);
+            assert_eq!(parse(once.to_string()).unwrap(), once, "{}", expr);
+        }
+    }
+}
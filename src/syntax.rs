@@ -0,0 +1,152 @@
+use crate::parser;
+use crate::{lex, Ast, Result};
+
+/// Options for the syntax of expressions that is not accepted by default.
+///
+/// [`eval`](crate::eval) and [`parse`](crate::parse) use the default syntax.
+/// Use [`Syntax::eval`] and [`Syntax::parse`], or
+/// [`Context::set_syntax`](crate::Context::set_syntax), to read expressions
+/// with other options.
+///
+/// ```
+/// # fn main() -> Result<(), mexe::MexeError> {
+/// use mexe::Syntax;
+///
+/// let syntax = Syntax::new().apostrophes(true);
+/// assert_eq!(syntax.eval("1'000'000 / 4")?, 250000.0);
+/// assert!(mexe::eval("1'000'000 / 4").is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Syntax {
+    pub(crate) apostrophes: bool,
+    pub(crate) number_format: NumberFormat,
+    pub(crate) lenient_literals: bool,
+    pub(crate) unicode_whitespace: bool,
+}
+
+/// How the decimals of numbers are written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NumberFormat {
+    /// `1234.56`: `.` separates the decimals, and `,` the arguments of
+    /// `if(c, a, b)`
+    #[default]
+    DecimalPoint,
+    /// `1.234,56`, as in many European locales: `,` separates the decimals,
+    /// `.` can separate groups of three digits before them, and `;` separates
+    /// the arguments of `if(c; a; b)`, as in spreadsheets. Outside of
+    /// parentheses, `;` still separates the statements of a program.
+    DecimalComma,
+}
+
+impl Syntax {
+    /// Creates the default syntax.
+    pub fn new() -> Syntax {
+        Syntax::default()
+    }
+
+    /// Whether apostrophes can separate groups of digits in numbers, as in
+    /// `1'000'000`, besides underscores. Defaults to `false`.
+    pub fn apostrophes(mut self, apostrophes: bool) -> Syntax {
+        self.apostrophes = apostrophes;
+        self
+    }
+
+    /// How the decimals of numbers are written. Defaults to
+    /// [`NumberFormat::DecimalPoint`].
+    ///
+    /// ```
+    /// # fn main() -> Result<(), mexe::MexeError> {
+    /// use mexe::{NumberFormat, Syntax};
+    ///
+    /// let syntax = Syntax::new().number_format(NumberFormat::DecimalComma);
+    /// assert_eq!(syntax.eval("1.234,5 * 2")?, 2469.0);
+    /// assert_eq!(syntax.parse("if(x > 0; x; 0,5)")?, mexe::parse("if(x > 0, x, 0.5)")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn number_format(mut self, number_format: NumberFormat) -> Syntax {
+        self.number_format = number_format;
+        self
+    }
+
+    /// Whether numbers can omit the digits before or after the decimal
+    /// separator, as in `.5` and `5.`. Defaults to `false`.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), mexe::MexeError> {
+    /// use mexe::Syntax;
+    ///
+    /// let syntax = Syntax::new().lenient_literals(true);
+    /// assert_eq!(syntax.eval(".5 + 5.")?, 5.5);
+    /// assert!(mexe::eval(".5 + 5.").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lenient_literals(mut self, lenient_literals: bool) -> Syntax {
+        self.lenient_literals = lenient_literals;
+        self
+    }
+
+    /// Whether all Unicode whitespace, such as no-break and thin spaces, is
+    /// accepted between tokens, besides ASCII whitespace. Defaults to
+    /// `false`.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), mexe::MexeError> {
+    /// use mexe::Syntax;
+    ///
+    /// let syntax = Syntax::new().unicode_whitespace(true);
+    /// assert_eq!(syntax.eval("2\u{A0}*\u{2009}3")?, 6.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unicode_whitespace(mut self, unicode_whitespace: bool) -> Syntax {
+        self.unicode_whitespace = unicode_whitespace;
+        self
+    }
+
+    /// Evaluates a numeric expression like [`eval`](crate::eval), with this
+    /// syntax.
+    ///
+    /// `T`: type of the expression. Usually a `&str` or a `String`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`eval`](crate::eval).
+    pub fn eval<T>(&self, expression: T) -> Result<f64>
+    where
+        T: AsRef<str>,
+    {
+        self.eval_str(expression.as_ref())
+    }
+
+    // Not generic, so that it is compiled once, in this crate, instead of in
+    // every crate that calls `eval`.
+    fn eval_str(&self, expression: &str) -> Result<f64> {
+        let tokens = lex::get_tokens_with(expression, self)?;
+        let mut failed = None;
+        let val = parser::parse_and_evaluate(&tokens, &mut failed)
+            .map_err(|err| lex::parse_error(expression, self, false, &tokens, err))?;
+        failed.map_or(Ok(val), Err)
+    }
+
+    /// Parses an expression into an [`Ast`] like [`parse`](crate::parse),
+    /// with this syntax.
+    ///
+    /// `T`: type of the expression. Usually a `&str` or a `String`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`parse`](crate::parse).
+    pub fn parse<T>(&self, expression: T) -> Result<Ast>
+    where
+        T: AsRef<str>,
+    {
+        let expression = expression.as_ref();
+        let tokens = lex::get_tokens_with(expression, self)?;
+        parser::parse_tree(&tokens)
+            .map_err(|err| lex::parse_error(expression, self, false, &tokens, err))
+    }
+}
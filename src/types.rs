@@ -1,10 +1,74 @@
+use crate::MexeError;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub(crate) enum Operator {
+/// A binary operator
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operator {
+    /// Sum (`+`)
     Add = b'+' as isize,
+    /// Subtraction (`-`)
     Sub = b'-' as isize,
+    /// Multiplication (`*`)
     Mul = b'*' as isize,
+    /// Division (`/`)
     Div = b'/' as isize,
+    /// Less than (`<`)
+    Lt,
+    /// Less than or equal to (`<=`)
+    Le,
+    /// Greater than (`>`)
+    Gt,
+    /// Greater than or equal to (`>=`)
+    Ge,
+    /// Equal to (`==`)
+    Eq,
+    /// Not equal to (`!=`)
+    Ne,
+    /// Logical and (`&&`)
+    And,
+    /// Logical or (`||`)
+    Or,
+    /// Bitwise and (`&`)
+    BitAnd,
+    /// Bitwise or (`|`)
+    BitOr,
+    /// Bitwise exclusive or (`xor`)
+    BitXor,
+    /// Left shift (`<<`)
+    Shl,
+    /// Arithmetic right shift (`>>`)
+    Shr,
+}
+
+impl Operator {
+    // Binding strength of the operator, as in C: `||` binds the loosest, then
+    // `&&`, `|`, `xor`, `&`, equality, comparisons, shifts, sums and products.
+    pub(crate) fn precedence(self) -> u8 {
+        match self {
+            Operator::Or => 1,
+            Operator::And => 2,
+            Operator::BitOr => 3,
+            Operator::BitXor => 4,
+            Operator::BitAnd => 5,
+            Operator::Eq | Operator::Ne => 6,
+            Operator::Lt | Operator::Le | Operator::Gt | Operator::Ge => 7,
+            Operator::Shl | Operator::Shr => 8,
+            Operator::Add | Operator::Sub => 9,
+            Operator::Mul | Operator::Div => 10,
+        }
+    }
+
+    // Result of an arithmetic operator, or `None` for the operators that
+    // produce booleans or work on integers.
+    pub(crate) fn apply(self, lhs: f64, rhs: f64) -> Option<f64> {
+        match self {
+            Operator::Add => Some(lhs + rhs),
+            Operator::Sub => Some(lhs - rhs),
+            Operator::Mul => Some(lhs * rhs),
+            Operator::Div => Some(lhs / rhs),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for Operator {
@@ -14,27 +78,115 @@ impl std::fmt::Display for Operator {
             Operator::Sub => write!(f, "-"),
             Operator::Mul => write!(f, "*"),
             Operator::Div => write!(f, "/"),
+            Operator::Lt => write!(f, "<"),
+            Operator::Le => write!(f, "<="),
+            Operator::Gt => write!(f, ">"),
+            Operator::Ge => write!(f, ">="),
+            Operator::Eq => write!(f, "=="),
+            Operator::Ne => write!(f, "!="),
+            Operator::And => write!(f, "&&"),
+            Operator::Or => write!(f, "||"),
+            Operator::BitAnd => write!(f, "&"),
+            Operator::BitOr => write!(f, "|"),
+            Operator::BitXor => write!(f, "xor"),
+            Operator::Shl => write!(f, "<<"),
+            Operator::Shr => write!(f, ">>"),
+        }
+    }
+}
+
+/// The result of evaluating an expression with a [`Context`](crate::Context)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    /// A number, from arithmetic
+    Number(f64),
+    /// A boolean, from comparisons and logical operators
+    Bool(bool),
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Value {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = MexeError;
+
+    fn try_from(value: Value) -> Result<f64, MexeError> {
+        match value {
+            Value::Number(n) => Ok(n),
+            Value::Bool(_) => Err(MexeError::TypeMismatch(
+                "expected a number, found a boolean".to_owned(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = MexeError;
+
+    fn try_from(value: Value) -> Result<bool, MexeError> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            Value::Number(_) => Err(MexeError::TypeMismatch(
+                "expected a boolean, found a number".to_owned(),
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
         }
     }
 }
 
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub(crate) enum Token {
+pub(crate) enum Token<'a> {
     LPar,
     RPar,
     Number(f64),
+    Ident(&'a str),
     Op(Operator),
-    EOI, // end of input
+    Not,
+    BitNot,
+    Question,
+    Colon,
+    Comma,
+    Assign,
+    Semicolon,
+    Newline, // only in programs, where it separates statements like `;`
+    EOI,     // end of input
 }
 
-impl std::fmt::Display for Token {
+impl std::fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
         match self {
             Token::LPar => write!(f, "("),
             Token::RPar => write!(f, ")"),
             Token::Number(n) => write!(f, "{}", n),
+            Token::Ident(name) => write!(f, "{}", name),
             Token::Op(op) => write!(f, "{}", op),
+            Token::Not => write!(f, "!"),
+            Token::BitNot => write!(f, "~"),
+            Token::Question => write!(f, "?"),
+            Token::Colon => write!(f, ":"),
+            Token::Comma => write!(f, ","),
+            Token::Assign => write!(f, "="),
+            Token::Semicolon => write!(f, ";"),
+            Token::Newline => write!(f, "\\n"),
             Token::EOI => write!(f, "EOI"),
         }
     }
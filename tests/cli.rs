@@ -0,0 +1,152 @@
+#![cfg(feature = "cli")]
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn mexe(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mexe"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+#[test]
+fn evaluates_arguments() {
+    let output = mexe(&["(1 + 2)", "*", "3"], "");
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "9\n");
+
+    let output = mexe(&["--", "-1", "-", "1"], "");
+    assert_eq!(stdout(&output), "-2\n");
+}
+
+#[test]
+fn evaluates_each_line_of_stdin() {
+    let output = mexe(&[], "1 + 1\n\n10 / 4\r\n");
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "2\n2.5\n");
+}
+
+#[test]
+fn errors_are_shown_with_a_caret() {
+    let output = mexe(&[], "1 + * 2\n2 * 2\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "4\n");
+    assert_eq!(
+        stderr(&output),
+        "error: Unexpected token: `*` at index 4\n  1 + * 2\n      ^\n"
+    );
+}
+
+#[test]
+fn errors_in_multi_line_arguments_show_their_line() {
+    let output = mexe(&["x = 1\ny = x + * 2"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "error: Unexpected token: `*` at index 14 (line 2, column 9)\n  y = x + * 2\n          ^\n"
+    );
+}
+
+#[test]
+fn strict_mode_rejects_division_by_zero() {
+    assert_eq!(stdout(&mexe(&["1 / 0"], "")), "inf\n");
+
+    let output = mexe(&["--strict", "1 / 0"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "error: Division by zero\n");
+}
+
+#[test]
+fn results_are_rounded_to_the_precision() {
+    let output = mexe(&["--precision", "2", "2 / 3"], "");
+    assert_eq!(stdout(&output), "0.67\n");
+
+    let output = mexe(&["--precision", "x", "2 / 3"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn json_output() {
+    let output = mexe(&["--json"], "1 + 1\n1 / 0\n\"x\"\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "{\"expression\":\"1 + 1\",\"result\":2}\n\
+         {\"expression\":\"1 / 0\",\"result\":null}\n\
+         {\"expression\":\"\\\"x\\\"\",\"error\":\"Invalid character at index 0\",\"index\":0}\n"
+    );
+}
+
+#[test]
+fn variables_persist_across_lines() {
+    let output = mexe(&[], "r = 2\nd = 2 * r; d * r\nd + x\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "2\n8\n");
+    assert_eq!(
+        stderr(&output),
+        "error: Variable `x` used before being defined at index 4\n  d + x\n      ^\n"
+    );
+
+    let output = mexe(&["r = 2;", "r * r"], "");
+    assert_eq!(stdout(&output), "4\n");
+}
+
+#[test]
+fn booleans_are_printed() {
+    let output = mexe(
+        &["--json"],
+        "qty = 12; qty > 10 && qty != 15\n!(1 < 2) + 1\n",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "{\"expression\":\"qty = 12; qty > 10 && qty != 15\",\"result\":true}\n\
+         {\"expression\":\"!(1 < 2) + 1\",\"error\":\"Type mismatch: `+` cannot be applied to a boolean\",\"index\":null}\n"
+    );
+
+    let output = mexe(&["--precision", "1", "--", "-1 <= 1"], "");
+    assert_eq!(stdout(&output), "true\n");
+}
+
+#[test]
+fn interactive_mode() {
+    let output = mexe(
+        &["--interactive"],
+        "1 + 2\nx = 3 * 4\nx * _1\n_1 + _3 + y\nz = 1 +\n",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "> _1 = 3\n> _2 = 12\n> _3 = 36\n> > > \n");
+    assert_eq!(
+        stderr(&output),
+        "error: Variable `y` used before being defined at index 10\n  \
+         _1 + _3 + y\n            ^\n\
+         error: Unexpected token: `EOI` at index 7\n  z = 1 +\n         ^\n"
+    );
+}
@@ -1,8 +1,9 @@
 #![recursion_limit = "256"]
-use glc::{grammar, Expression, Grammar};
+use glc::{grammar, Expression, Grammar, RuleBody, Symbol};
+use mexe::{Ast, Operator, Spacing, Syntax};
 
 fn grammar() -> Grammar {
-    grammar!{
+    grammar! {
         E => T E_;
         E_ => PM T E_;
         E_ => "";
@@ -34,6 +35,43 @@ fn grammar() -> Grammar {
     }
 }
 
+// Same as `grammar`, but numbers can also omit the digits on either side of
+// the point, as accepted with `Syntax::lenient_literals`.
+fn lenient_grammar() -> Grammar {
+    grammar! {
+        E => T E_;
+        E_ => PM T E_;
+        E_ => "";
+        E_ => "";
+        E_ => "";
+        PM => "+", "-";
+        T => F T_;
+        T_ => MD F T_;
+        MD => "*", "/";
+        T_ => "";
+        T_ => "";
+        T_ => "";
+        F => LP E RP;
+        F => M LP E RP;
+        F => N;
+        F => M N;
+        LP => "(";
+        RP => ")";
+        M => "-";
+        N => D DEC;
+        N => P D;
+        N => D P;
+        D => D OD;
+        OD => D;
+        OD => "";
+        OD => "";
+        OD => "";
+        D => "0", "1", "2", "3", "4", "5", "6", "7", "8", "9";
+        DEC => P D;
+        P => "."
+    }
+}
+
 #[test]
 #[ignore]
 fn without_bounds() {
@@ -49,22 +87,17 @@ fn random_exprs(bound: u64) {
     let grammar = grammar();
     let mut exprs = 0;
 
-    'der: while exprs < bound {
+    while exprs < bound {
         exprs += 1;
-        let mut d = grammar.start_derivation();
-        let mut count = 0;
-
-        while !d.is_done() {
-            count += 1;
-            d.derive_step(&grammar);
 
-            if count > 10_000 {
+        let (e, count) = match derive_expr(&grammar) {
+            Some(derived) => derived,
+            None => {
                 println!("skipped");
-                continue 'der;
+                continue;
             }
-        }
+        };
 
-        let e: Expression = d.into();
         let mexe = mexe::eval(e.to_string()).unwrap();
         let meval = meval::eval_str(e.to_string()).unwrap();
         println!("{}\nval: {}, steps: {}\n", &e, mexe, count);
@@ -74,3 +107,205 @@ fn random_exprs(bound: u64) {
         }
     }
 }
+
+#[test]
+fn round_trip() {
+    let grammar = grammar();
+    let spacings = [Spacing::Spaced, Spacing::Grouped, Spacing::Compact];
+    let mut seed = 1;
+
+    // the grammar has no `!` or `~`, which can follow a minus
+    let negations = ["-!a", "-~(a + 1) * -!-b", "1 - -~~a", "-(-!a)"];
+    let derived = (0..1_000).filter_map(|_| derive_seeded(&grammar, &mut seed));
+
+    for e in negations.iter().map(|e| e.to_string()).chain(derived) {
+        let ast = mexe::parse(&e).unwrap();
+
+        for spacing in spacings.iter() {
+            let printed = ast.display(*spacing).to_string();
+            assert_eq!(mexe::parse(&printed).unwrap(), ast, "{} -> {}", e, printed);
+        }
+
+        let sexpr = mexe::to_sexpr(&ast);
+        assert_eq!(
+            mexe::parse_sexpr(&sexpr).unwrap(),
+            ast,
+            "{} -> {}",
+            e,
+            sexpr
+        );
+    }
+}
+
+#[test]
+fn lenient_literals() {
+    let grammar = lenient_grammar();
+    let lenient = Syntax::new().lenient_literals(true);
+    let mut seed = 2;
+
+    for _ in 0..1_000 {
+        let e = match derive_seeded(&grammar, &mut seed) {
+            Some(e) => e,
+            None => continue,
+        };
+
+        let strict = with_zeros(&e);
+        let val = lenient.eval(&e).unwrap();
+        let expected = mexe::eval(&strict).unwrap();
+
+        if !val.is_nan() || !expected.is_nan() {
+            assert_eq!(val, expected, "{} -> {}", e, strict);
+        }
+
+        // the strict syntax only accepts the expressions with both sides
+        assert_eq!(mexe::eval(&e).is_ok(), e == strict, "{}", e);
+    }
+}
+
+#[test]
+fn generated_expressions_simplify_to_a_fixed_point() {
+    let mut seed = 5;
+
+    for _ in 0..10_000 {
+        let ast = generate(&mut seed, 6);
+        let once = mexe::simplify(&ast);
+
+        assert_eq!(mexe::simplify(&once), once, "{}", ast);
+        assert_eq!(mexe::parse(once.to_string()).unwrap(), once, "{}", ast);
+
+        // the rules assume real arithmetic, where dividing by zero is
+        // undefined, and rounding errors grow near a division by zero
+        match value(&ast) {
+            Some(expected) if expected.abs() < 1e6 => {
+                let actual = value(&once).unwrap();
+                let error = (expected - actual).abs() / expected.abs().max(1.0);
+                assert!(error < 1e-9, "{} = {} vs {}", ast, expected, once);
+            }
+            _ => (),
+        }
+    }
+}
+
+// Adds the digits omitted around the points of a lenient expression.
+fn with_zeros(expr: &str) -> String {
+    let chars: Vec<char> = expr.chars().collect();
+    let is_digit =
+        |i: Option<usize>| matches!(i.and_then(|i| chars.get(i)), Some(c) if c.is_ascii_digit());
+    let mut strict = String::new();
+
+    for (i, c) in chars.iter().enumerate() {
+        if *c == '.' && !is_digit(i.checked_sub(1)) {
+            strict.push('0');
+        }
+
+        strict.push(*c);
+
+        if *c == '.' && !is_digit(Some(i + 1)) {
+            strict.push('0');
+        }
+    }
+
+    strict
+}
+
+fn derive_expr(grammar: &Grammar) -> Option<(Expression, usize)> {
+    let mut d = grammar.start_derivation();
+    let mut count = 0;
+
+    while !d.is_done() {
+        count += 1;
+        d.derive_step(grammar);
+
+        if count > 10_000 {
+            return None;
+        }
+    }
+
+    Some((d.into(), count))
+}
+
+// Same as `derive_expr`, but the rules are picked with a generator seeded with
+// `seed` instead of glc's thread-local one, so that a failure is reproduced
+// on every run.
+fn derive_seeded(grammar: &Grammar, seed: &mut u64) -> Option<String> {
+    let mut symbols = vec![Symbol::NonTerminal(grammar.0.clone())];
+    let mut expr = String::new();
+    let mut count = 0;
+
+    while let Some(symbol) = symbols.pop() {
+        let nt = match symbol {
+            Symbol::Terminal(t) => {
+                expr.push_str(&t.0);
+                continue;
+            }
+            Symbol::NonTerminal(nt) => nt,
+        };
+
+        count += 1;
+
+        if count > 10_000 {
+            return None;
+        }
+
+        let rules: Vec<_> = grammar.1.iter().filter(|rule| rule.0 == nt).collect();
+        let seq = match &rules[next(seed, rules.len())].1 {
+            RuleBody::Sequence(seq) => seq,
+            RuleBody::Or(or) => &or.0[next(seed, or.0.len())],
+        };
+
+        symbols.extend(seq.0.iter().rev().cloned());
+    }
+
+    Some(expr)
+}
+
+// Random arithmetic expressions over `x`, `y`, `z` and small integers, from
+// `seed`, as `derive_seeded` picks its rules.
+fn generate(seed: &mut u64, depth: u32) -> Ast {
+    match next(seed, if depth == 0 { 2 } else { 8 }) {
+        0 => Ast::Number([0.0, 1.0, 2.0, 3.0, 0.1, 2.5, 7.0, 100.0][next(seed, 8)]),
+        1 => Ast::Var(["x", "y", "z"][next(seed, 3)].to_owned()),
+        2 => Ast::Neg(Box::new(generate(seed, depth - 1))),
+        op => {
+            let op = [Operator::Add, Operator::Sub, Operator::Mul, Operator::Div][op % 4];
+            let lhs = generate(seed, depth - 1);
+            Ast::Binary(op, Box::new(lhs), Box::new(generate(seed, depth - 1)))
+        }
+    }
+}
+
+// Evaluates arithmetic at `x = 1.75`, `y = -2.5` and `z = 0.625`, or returns
+// `None` if a step is not finite.
+fn value(ast: &Ast) -> Option<f64> {
+    let val = match ast {
+        Ast::Number(n) => *n,
+        Ast::Var(name) => match name.as_str() {
+            "x" => 1.75,
+            "y" => -2.5,
+            _ => 0.625,
+        },
+        Ast::Neg(inner) => -value(inner)?,
+        Ast::Binary(op, lhs, rhs) => {
+            let (lhs, rhs) = (value(lhs)?, value(rhs)?);
+
+            match op {
+                Operator::Add => lhs + rhs,
+                Operator::Sub => lhs - rhs,
+                Operator::Mul => lhs * rhs,
+                Operator::Div => lhs / rhs,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    Some(val).filter(|val| val.is_finite())
+}
+
+// The next number below `n` from a linear congruential generator.
+fn next(seed: &mut u64, n: usize) -> usize {
+    *seed = seed
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    (*seed >> 33) as usize % n
+}
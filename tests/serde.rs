@@ -0,0 +1,72 @@
+#![cfg(feature = "serde")]
+use mexe::{Ast, MexeError, Operator};
+
+#[test]
+fn ast_round_trip() {
+    let ast = mexe::parse("-(x - 1.5) * y").unwrap();
+    let json = serde_json::to_string(&ast).unwrap();
+
+    assert_eq!(
+        json,
+        r#"{"Binary":["Mul",{"Neg":{"Binary":["Sub",{"Var":"x"},{"Number":1.5}]}},{"Var":"y"}]}"#
+    );
+    assert_eq!(serde_json::from_str::<Ast>(&json).unwrap(), ast);
+}
+
+#[test]
+fn operator_round_trip() {
+    let json = serde_json::to_string(&Operator::Div).unwrap();
+
+    assert_eq!(json, r#""Div""#);
+    assert_eq!(
+        serde_json::from_str::<Operator>(&json).unwrap(),
+        Operator::Div
+    );
+}
+
+#[test]
+fn error_round_trip() {
+    let err = mexe::eval("1 + $").unwrap_err();
+    let json = serde_json::to_string(&err).unwrap();
+
+    assert_eq!(json, r#"{"InvalidCharacter":4}"#);
+    assert_eq!(serde_json::from_str::<MexeError>(&json).unwrap(), err);
+
+    let err = mexe::eval("(1 + 2").unwrap_err();
+    let json = serde_json::to_string(&err).unwrap();
+
+    assert_eq!(json, r#"{"UnexpectedToken":["EOI",6]}"#);
+    assert_eq!(
+        serde_json::from_str::<MexeError>(&json).unwrap().index(),
+        Some(6)
+    );
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Config {
+    #[serde(deserialize_with = "mexe::serde::expr_f64")]
+    value: f64,
+}
+
+#[test]
+fn expr_f64_accepts_numbers_and_expressions() {
+    let value = |json: &str| serde_json::from_str::<Config>(json).map(|c| c.value);
+
+    assert_eq!(value(r#"{"value": 3}"#).unwrap(), 3.0);
+    assert_eq!(value(r#"{"value": -3}"#).unwrap(), -3.0);
+    assert_eq!(value(r#"{"value": 2.5}"#).unwrap(), 2.5);
+    assert_eq!(value(r#"{"value": "60 * 60 * 24"}"#).unwrap(), 86400.0);
+}
+
+#[test]
+fn expr_f64_reports_invalid_expressions() {
+    let err = serde_json::from_str::<Config>(r#"{"value": "1 +"}"#).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("invalid expression `1 +`: Unexpected token: `EOI`"));
+
+    let err = serde_json::from_str::<Config>(r#"{"value": true}"#).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("expected a number or a string with an arithmetic expression"));
+}